@@ -1,12 +1,10 @@
 use proc_macro::TokenStream;
-use proc_macro2;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{self, spanned::Spanned, LitStr};
 
 // attributes 可以注册多个惰性属性
 #[proc_macro_derive(Builder,attributes(builder))]
 pub fn derive(input: TokenStream) -> TokenStream {
-    let _ = input;
     let st = syn::parse_macro_input!(input as syn::DeriveInput);
     // eprintln!("{input_ast:#?}");
 
@@ -27,7 +25,10 @@ fn get_fields_from_derive_input(st: &syn::DeriveInput) -> syn::Result<&StructFie
     {
         return Ok(named);
     };
-    Err(syn::Error::new_spanned(st, "解析字段名错误"))
+    Err(syn::Error::new_spanned(
+        st,
+        "`Builder` can only be derived for structs with named fields",
+    ))
 }
 
 fn generate_builder_struct_fields_def(
@@ -41,7 +42,7 @@ fn generate_builder_struct_fields_def(
             Ok(quote! {
                 std::option::Option<#inner_type>
             })
-        } else if get_user_specified_ident_for_vec(&f)?.is_some() {
+        } else if get_user_specified_ident_for_vec(f)?.is_some() {
             let origin_type = &f.ty;
             Ok(quote! {
                 #origin_type
@@ -56,13 +57,23 @@ fn generate_builder_struct_fields_def(
 
     let types = fields_type?;
 
+    // 开启 serde 时，缺失的键保持为未设置状态，Option 取 None，each 字段取空 Vec
+    let serde_enabled = get_struct_flag(st, "serde")?;
+    let fields_attrs = fields.iter().map(|_| {
+        if serde_enabled {
+            quote! { #[serde(default)] }
+        } else {
+            proc_macro2::TokenStream::new()
+        }
+    });
+
     // 在生成类型的时候要使用绝对路径避免与当前定义的类型冲突
     // #(重复的内容必须是实现了迭代器的数据)*
     let ret: proc_macro2::TokenStream = quote! {
-        #(#fields_ident:#types),*
+        #(#fields_attrs #fields_ident:#types),*
     };
 
-    Ok(ret.into())
+    Ok(ret)
 }
 
 fn generate_builder_struct_factory_init_clauses(
@@ -71,6 +82,7 @@ fn generate_builder_struct_factory_init_clauses(
     // 也可以像 generate_builder_struct_fields_def 一样生成
 
     let fields = get_fields_from_derive_input(st)?;
+    let constructor = get_struct_flag(st, "constructor")?;
 
     let init_clauses:syn::Result<Vec<_>> = fields
         .iter()
@@ -79,10 +91,15 @@ fn generate_builder_struct_factory_init_clauses(
             // 这里为什么加个 ? 就要把其他的返回都加上 Ok 包裹？
             // 因为当 get_user_specified_ident_for_vec 报错是就会抛出错误，而错误类型是 Result,
             // map 接收到的返回数据类型就有两种：TokenStream 和 Result，从而出现类型冲突，所以要进行统一化处理
-            if get_user_specified_ident_for_vec(&f)?.is_some() {
+            if get_user_specified_ident_for_vec(f)?.is_some() {
                 Ok(quote! {
                     #ident: std::vec::Vec::new()
                 })
+            } else if constructor && is_required_field(f)? {
+                // 必填字段的值由 builder() 的参数直接传入
+                Ok(quote! {
+                    #ident: std::option::Option::Some(#ident)
+                })
             } else {
                 Ok(quote! {
                     #ident: std::option::Option::None
@@ -91,15 +108,38 @@ fn generate_builder_struct_factory_init_clauses(
         })
         .collect();
 
-    Ok(init_clauses?)
+    init_clauses
+}
+
+// 指定 #[builder(constructor)] 后，所有必填字段按声明顺序作为 builder() 的参数
+fn generate_constructor_args(st: &syn::DeriveInput) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let mut args = Vec::new();
+    if !get_struct_flag(st, "constructor")? {
+        return Ok(args);
+    }
+    for field in get_fields_from_derive_input(st)? {
+        if is_required_field(field)? {
+            let ident = &field.ident;
+            let type_ = &field.ty;
+            args.push(quote! {
+                #ident:#type_
+            });
+        }
+    }
+    Ok(args)
 }
 
 fn generate_setter_functions(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let fields = get_fields_from_derive_input(st)?;
+    let constructor = get_struct_flag(st, "constructor")?;
 
     let mut final_tokenstream = proc_macro2::TokenStream::new();
 
     for field in fields {
+        // 必填字段已经通过 builder() 的参数传入，不再生成 setter
+        if constructor && is_required_field(field)? {
+            continue;
+        }
         let ident = &field.ident;
         let type_ = &field.ty;
         let tokenstream_piece = if let Some(inner_type) = get_generic_inner_type(type_, "Option") {
@@ -121,10 +161,23 @@ fn generate_setter_functions(st: &syn::DeriveInput) -> syn::Result<proc_macro2::
                     self
                 }
             });
+            // 除了逐个添加，再生成批量追加和清空集合的方法，方便从其他来源合并数据
+            let extend_ident = format_ident!("{}_extend", ident.as_ref().unwrap());
+            let clear_ident = format_ident!("{}_clear", ident.as_ref().unwrap());
+            tokenstream.extend(quote! {
+                fn #extend_ident<I: std::iter::IntoIterator<Item = #inner_type>>(&mut self, iter: I) -> &mut Self{
+                    self.#ident.extend(iter);
+                    self
+                }
+                fn #clear_ident(&mut self) -> &mut Self{
+                    self.#ident.clear();
+                    self
+                }
+            });
             if user_specified_ident != ident.as_ref().unwrap() {
                 tokenstream.extend(quote! {
                     fn #ident(&mut self,#ident:#type_) -> &mut Self{
-                        self.#ident = #ident.clone();
+                        self.#ident = #ident;
                         self
                     }
                 });
@@ -150,9 +203,8 @@ fn generate_build_function(st: &syn::DeriveInput) -> syn::Result<proc_macro2::To
     let mut checker_code_pieces = Vec::new();
     for field in fields {
         let ident = &field.ident;
-        let type_ = &field.ty;
         // 只对不是`Option`类型且没有指定each属性的字段生成校验逻辑
-        if get_generic_inner_type(type_, "Option").is_none() && get_user_specified_ident_for_vec(field)?.is_none() {
+        if is_required_field(field)? {
             checker_code_pieces.push(quote! {
                 if self.#ident.is_none() {
                     let err = format!("{} field is missing",stringify!(#ident));
@@ -162,18 +214,39 @@ fn generate_build_function(st: &syn::DeriveInput) -> syn::Result<proc_macro2::To
         }
     }
 
+    // 默认情况下 build 会克隆字段，builder 可以重复使用；
+    // 指定 #[builder(take)] 后直接把值从 builder 中取出，字段类型不再需要实现 Clone，
+    // 但 build 成功之后 builder 就被清空了，只能使用一次
+    let take = get_struct_flag(st, "take")?;
+
     let mut fill_result_clauses = Vec::new();
     for field in fields {
         let ident = &field.ident;
         let type_ = &field.ty;
         // 需要先判断是有自定 each ，再判断是否是 Option，因为 Option比 each 范围更广
         if get_user_specified_ident_for_vec(field)?.is_some() {
-            fill_result_clauses.push(quote! {
-                #ident:self.#ident.clone()
-            });
+            if take {
+                fill_result_clauses.push(quote! {
+                    #ident:std::mem::take(&mut self.#ident)
+                });
+            } else {
+                fill_result_clauses.push(quote! {
+                    #ident:self.#ident.clone()
+                });
+            }
         } else if get_generic_inner_type(type_, "Option").is_none() {
+            if take {
+                fill_result_clauses.push(quote! {
+                    #ident:self.#ident.take().unwrap()
+                });
+            } else {
+                fill_result_clauses.push(quote! {
+                    #ident:self.#ident.clone().unwrap()
+                });
+            }
+        } else if take {
             fill_result_clauses.push(quote! {
-                #ident:self.#ident.clone().unwrap()
+                #ident:self.#ident.take()
             });
         } else {
             fill_result_clauses.push(quote! {
@@ -182,9 +255,20 @@ fn generate_build_function(st: &syn::DeriveInput) -> syn::Result<proc_macro2::To
         }
     }
 
+    let build_doc = if take {
+        quote! {
+            /// Moves the collected values out of the builder. The builder is
+            /// left empty afterwards, so it is meant to be used only once.
+        }
+    } else {
+        proc_macro2::TokenStream::new()
+    };
+
     let struct_name_ident = &st.ident;
+    let (_, type_generics, _) = st.generics.split_for_impl();
     let token_stream = quote! {
-        pub fn build(&mut self) -> std::result::Result<#struct_name_ident,std::boxed::Box<dyn std::error::Error>>{
+        #build_doc
+        pub fn build(&mut self) -> std::result::Result<#struct_name_ident #type_generics,std::boxed::Box<dyn std::error::Error>>{
             #(#checker_code_pieces)*
 
             let ret = #struct_name_ident {
@@ -198,6 +282,30 @@ fn generate_build_function(st: &syn::DeriveInput) -> syn::Result<proc_macro2::To
     Ok(token_stream)
 }
 
+// 不是`Option`类型且没有指定each属性的字段必须在 build 之前设置
+fn is_required_field(field: &syn::Field) -> syn::Result<bool> {
+    Ok(get_generic_inner_type(&field.ty, "Option").is_none()
+        && get_user_specified_ident_for_vec(field)?.is_none())
+}
+
+// 查找结构体上形如 #[builder(serde)] 这种不带值的开关
+fn get_struct_flag(st: &syn::DeriveInput, name: &str) -> syn::Result<bool> {
+    let mut flag = false;
+    for attr in &st.attrs {
+        if attr.path().is_ident("builder") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident(name) {
+                    flag = true;
+                } else {
+                    skip_meta_value(&meta)?;
+                }
+                Ok(())
+            })?;
+        }
+    }
+    Ok(flag)
+}
+
 fn get_generic_inner_type<'a>(
     t: &'a syn::Type,
     outer_ident_name: &'a str,
@@ -223,38 +331,163 @@ fn get_generic_inner_type<'a>(
             }
         }
     }
-    return None;
+    None
 }
 
 fn get_user_specified_ident_for_vec(field: &syn::Field) -> syn::Result<Option<syn::Ident>> {
+    let mut ident = None;
     for attr in &field.attrs {
         if attr.path().is_ident("builder") {
-            let mut ident = None;
             // 旧版本sys 的 parse_meta 方法的结果现在在 attr_meta 中
-            let ret = attr.parse_nested_meta(|meta| {
+            attr.parse_nested_meta(|meta| {
                 if meta.path.is_ident("each") {
-                    let value = meta.value()?;
-                    let s: LitStr = value.parse()?;
-                    ident = Some(syn::Ident::new(s.value().as_str(), attr.span()));
-                    ()
+                    let s: LitStr = meta.value()?.parse()?;
+                    ident = Some(s.parse()?);
                 } else {
-                    if let syn::Meta::List(ref list) = attr.meta {
-                        eprintln!("metalist,{list:#?}");
-                        return Err(syn::Error::new_spanned(list, r#"expected `builder(each = "...")`"#));
-                    }
+                    skip_meta_value(&meta)?;
                 }
                 Ok(())
-            });
-            if let Err(err) = ret {
-                return Err(err);
+            })?;
+        }
+    }
+    Ok(ident)
+}
+
+// 结构体上 #[builder(...)] 可以使用的键
+const STRUCT_ATTRIBUTE_KEYS: &[&str] = &["serde", "take", "constructor"];
+// 字段上 #[builder(...)] 可以使用的键
+const FIELD_ATTRIBUTE_KEYS: &[&str] = &["each"];
+
+// 在生成代码之前先把所有的 #[builder(...)] 属性检查一遍，遇到错误不立即返回，
+// 而是收集起来最后用 syn::Error::combine 合并，这样用户一次编译就能看到所有问题
+fn check_builder_attributes(st: &syn::DeriveInput) -> syn::Result<()> {
+    let mut errors = Vec::new();
+
+    for attr in st.attrs.iter().filter(|a| a.path().is_ident("builder")) {
+        let ret = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("serde") {
+                // 生成的代码依赖调用方的 serde，需要显式开启 feature 才允许使用
+                if !cfg!(feature = "serde") {
+                    errors.push(syn::Error::new_spanned(
+                        &meta.path,
+                        "`builder(serde)` requires the `serde` feature of derive_builder",
+                    ));
+                }
+            } else if meta.path.is_ident("take") || meta.path.is_ident("constructor") {
+                // 不带值的开关，没有需要额外检查的内容
+            } else {
+                errors.push(unknown_key_error(
+                    &meta.path,
+                    STRUCT_ATTRIBUTE_KEYS,
+                    "expected `builder(serde)`, `builder(take)` or `builder(constructor)`",
+                ));
+                skip_meta_value(&meta)?;
+            }
+            Ok(())
+        });
+        if let Err(err) = ret {
+            errors.push(err);
+        }
+    }
+
+    match get_fields_from_derive_input(st) {
+        Ok(fields) => {
+            for field in fields {
+                for attr in field.attrs.iter().filter(|a| a.path().is_ident("builder")) {
+                    let ret = attr.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("each") {
+                            let s: LitStr = meta.value()?.parse()?;
+                            if s.parse::<syn::Ident>().is_err() {
+                                errors.push(syn::Error::new(
+                                    s.span(),
+                                    format!("`{}` is not a valid method name", s.value()),
+                                ));
+                            }
+                            if get_generic_inner_type(&field.ty, "Vec").is_none() {
+                                errors.push(syn::Error::new_spanned(
+                                    &field.ty,
+                                    "`builder(each = \"...\")` can only be used on a Vec field",
+                                ));
+                            }
+                        } else {
+                            errors.push(unknown_key_error(
+                                &meta.path,
+                                FIELD_ATTRIBUTE_KEYS,
+                                r#"expected `builder(each = "...")`"#,
+                            ));
+                            skip_meta_value(&meta)?;
+                        }
+                        Ok(())
+                    });
+                    if let Err(err) = ret {
+                        errors.push(err);
+                    }
+                }
             }
-            return Ok(ident);
         }
+        Err(err) => errors.push(err),
+    }
+
+    let mut errors = errors.into_iter();
+    match errors.next() {
+        Some(mut first) => {
+            for err in errors {
+                first.combine(err);
+            }
+            Err(first)
+        }
+        None => Ok(()),
+    }
+}
+
+// 生成指向未知键的错误，如果键名和某个已知的键很接近，就在错误信息里给出提示
+fn unknown_key_error(path: &syn::Path, known_keys: &[&str], expected: &str) -> syn::Error {
+    let key = quote!(#path).to_string().replace(' ', "");
+    let suggestion = known_keys
+        .iter()
+        .map(|k| (edit_distance(&key, k), k))
+        .filter(|(d, _)| *d <= 2)
+        .min_by_key(|(d, _)| *d);
+    match suggestion {
+        Some((_, k)) => syn::Error::new_spanned(path, format!("{expected}, did you mean `{k}`?")),
+        None => syn::Error::new_spanned(path, expected),
     }
-    Ok(None)
+}
+
+// 跳过未知键后面的值，例如 `eahc = "arg"` 中的 `= "arg"` 或 `foo(...)` 中的括号，
+// 这样 parse_nested_meta 才能继续解析后面的键，而不是在这里就报错退出
+fn skip_meta_value(meta: &syn::meta::ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        let value = meta.value()?;
+        while !value.is_empty() && !value.peek(syn::Token![,]) {
+            value.parse::<proc_macro2::TokenTree>()?;
+        }
+    } else if meta.input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in meta.input);
+        content.parse::<proc_macro2::TokenStream>()?;
+    }
+    Ok(())
+}
+
+// 计算两个字符串之间的编辑距离（Levenshtein distance），用于给拼写错误的键提供建议
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
 }
 
 fn do_expand(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    check_builder_attributes(st)?;
+
     // 获取到结构体的名字 ident;
     let struct_name_ident = st.ident.clone();
     // 获取到结构体的名字
@@ -270,21 +503,34 @@ fn do_expand(st: &syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let builder_struct_factory_init_clauses = generate_builder_struct_factory_init_clauses(st)?;
     let setter_functions = generate_setter_functions(st)?;
     let build_function = generate_build_function(st)?;
+    let constructor_args = generate_constructor_args(st)?;
+
+    let builder_derives = if get_struct_flag(st, "serde")? {
+        quote! { #[derive(::serde::Deserialize)] }
+    } else {
+        proc_macro2::TokenStream::new()
+    };
+
+    // builder 结构体的字段和原结构体一一对应，所以直接沿用原结构体的泛型参数，
+    // 这样带有生命周期的引用字段（例如 `&'a str`）也可以使用
+    let generics = &st.generics;
+    let (impl_generics, type_generics, where_clause) = st.generics.split_for_impl();
 
     // 使用 quote! 插入并生成新的 proc_macro2::TokenStream
     let ret = quote! {
-        pub struct #builder_name_ident {
+        #builder_derives
+        pub struct #builder_name_ident #generics #where_clause {
             #builder_struct_fields_def
         }
 
-        impl #builder_name_ident{
+        impl #impl_generics #builder_name_ident #type_generics #where_clause {
             #setter_functions
 
             #build_function
         }
 
-        impl #struct_name_ident {
-            pub fn builder() -> #builder_name_ident {
+        impl #impl_generics #struct_name_ident #type_generics #where_clause {
+            pub fn builder(#(#constructor_args),*) -> #builder_name_ident #type_generics {
                 #builder_name_ident {
                     #(#builder_struct_factory_init_clauses),*
                 }
@@ -0,0 +1,39 @@
+// Since every builder field is either an Option or a Vec for `each` fields, the
+// builder is a natural "partial config" type. With the `serde` feature of
+// derive_builder enabled, a struct-level #[builder(serde)] attribute derives
+// serde::Deserialize on the generated builder. Keys missing from the input
+// leave the corresponding field unset, and the remaining fields can still be
+// filled in through the setters before calling build().
+//
+// The generated code refers to ::serde, so the calling crate needs its own
+// dependency on serde with the "derive" feature.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(serde)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(each = "env")]
+    env: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let mut builder: CommandBuilder =
+        serde_json::from_str(r#"{ "executable": "cargo", "args": ["build"] }"#).unwrap();
+
+    let command = builder.arg("--release".to_owned()).build().unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert!(command.env.is_empty());
+    assert!(command.current_dir.is_none());
+
+    let mut builder: CommandBuilder = serde_json::from_str(r#"{ "args": ["test"] }"#).unwrap();
+    assert!(builder.build().is_err());
+
+    let command = builder.executable("cargo".to_owned()).build().unwrap();
+    assert_eq!(command.args, vec!["test"]);
+}
@@ -0,0 +1,14 @@
+// Without the `serde` feature of derive_builder, #[builder(serde)] is rejected
+// with an error pointing at the attribute rather than producing code that
+// fails to resolve ::serde somewhere inside the expansion.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(serde)]
+pub struct Command {
+    executable: String,
+    current_dir: Option<String>,
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+// All problems with #[builder(...)] attributes are reported in a single
+// compilation, each pointing at the offending key or value, rather than only
+// the first one. Keys that look like a misspelling of a known key come with a
+// suggestion.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(sedre)]
+pub struct Command {
+    executable: String,
+    #[builder(eahc = "arg")]
+    args: Vec<String>,
+    #[builder(each = "not an ident")]
+    env: Vec<String>,
+    #[builder(each = "dir", unknown(key))]
+    current_dir: Option<String>,
+}
+
+fn main() {}
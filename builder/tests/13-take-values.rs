@@ -0,0 +1,51 @@
+// By default build() clones every field out of the builder so that the same
+// builder can be used again, which requires all field types to be Clone.
+//
+// With a struct-level #[builder(take)] attribute, build() moves the values out
+// of the builder instead (Option::take for ordinary fields, std::mem::take for
+// `each` collections). This allows fields such as boxed trait objects that do
+// not implement Clone, and borrowed fields that tie the struct to a lifetime.
+// The builder is left empty afterwards and is meant to be used only once.
+
+use derive_builder::Builder;
+
+pub trait Handler {
+    fn handle(&self, input: &str) -> usize;
+}
+
+struct Len;
+
+impl Handler for Len {
+    fn handle(&self, input: &str) -> usize {
+        input.len()
+    }
+}
+
+#[derive(Builder)]
+#[builder(take)]
+pub struct Server<'a> {
+    name: &'a str,
+    handler: Box<dyn Handler>,
+    #[builder(each = "hook")]
+    hooks: Vec<Box<dyn Handler>>,
+    fallback: Option<Box<dyn Handler>>,
+}
+
+fn main() {
+    let name = String::from("server");
+    let mut builder = Server::builder();
+    let server = builder
+        .name(&name)
+        .handler(Box::new(Len))
+        .hook(Box::new(Len))
+        .build()
+        .unwrap();
+
+    assert_eq!(server.name, "server");
+    assert_eq!(server.handler.handle("abc"), 3);
+    assert_eq!(server.hooks.len(), 1);
+    assert!(server.fallback.is_none());
+
+    // The values have been moved out, so the builder starts over.
+    assert!(builder.build().is_err());
+}
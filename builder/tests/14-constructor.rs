@@ -0,0 +1,34 @@
+// For structs with only a couple of mandatory fields, calling a setter for
+// each of them is verbose. A struct-level #[builder(constructor)] attribute
+// makes every required field (not an Option and not an `each` field) a
+// positional argument of builder(), in declaration order. Only the remaining
+// optional fields get setters.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(constructor)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+    verbose: bool,
+}
+
+fn main() {
+    let command = Command::builder("cargo".to_owned(), true)
+        .arg("build".to_owned())
+        .current_dir("..".to_owned())
+        .build()
+        .unwrap();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build"]);
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+    assert!(command.verbose);
+
+    let command = Command::builder("rustc".to_owned(), false).build().unwrap();
+    assert_eq!(command.executable, "rustc");
+    assert!(command.args.is_empty());
+}
@@ -0,0 +1,32 @@
+// Fields with #[builder(each = "...")] also get a `<field>_extend` method that
+// appends every element of any IntoIterator, and a `<field>_clear` method that
+// empties the collection, so lists coming from other sources can be added
+// without writing a loop around the one-at-a-time method.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(each = "env")]
+    env: Vec<String>,
+}
+
+fn main() {
+    let defaults = vec!["--verbose".to_owned(), "--locked".to_owned()];
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .args_extend(defaults)
+        .env("A=1".to_owned())
+        .env_clear()
+        .env_extend(["B=2", "C=3"].iter().map(|s| s.to_string()))
+        .build()
+        .unwrap();
+
+    assert_eq!(command.args, vec!["build", "--verbose", "--locked"]);
+    assert_eq!(command.env, vec!["B=2", "C=3"]);
+}
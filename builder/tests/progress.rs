@@ -10,4 +10,13 @@ fn tests() {
     t.pass("tests/07-repeated-field.rs");
     t.compile_fail("tests/08-unrecognized-attribute.rs");
     t.pass("tests/09-redefined-prelude-types.rs");
+    if cfg!(feature = "serde") {
+        t.pass("tests/10-serde.rs");
+    } else {
+        t.compile_fail("tests/11-serde-feature-disabled.rs");
+    }
+    t.compile_fail("tests/12-attribute-errors.rs");
+    t.pass("tests/13-take-values.rs");
+    t.pass("tests/14-constructor.rs");
+    t.pass("tests/15-each-extend.rs");
 }
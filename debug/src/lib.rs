@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 
 use proc_macro::{TokenStream};
-use quote::quote;
-use syn::{parse_macro_input, parse_quote, visit::{self, Visit}, DeriveInput, LitStr};
+use quote::{format_ident, quote, quote_spanned};
+use syn::{parse_macro_input, spanned::Spanned, parse_quote, visit::{self, Visit}, DeriveInput, LitStr};
 
 #[proc_macro_derive(CustomDebug,attributes(debug))]
 pub fn derive(input: TokenStream) -> TokenStream {
@@ -13,101 +13,354 @@ pub fn derive(input: TokenStream) -> TokenStream {
     }
 }
 
-type StructFields = syn::punctuated::Punctuated<syn::Field, syn::Token![,]>;
-fn get_fields_from_derive_input(st:&syn::DeriveInput) -> syn::Result<&StructFields> {
-    if let syn::Data::Struct(syn::DataStruct{
-        fields:syn::Fields::Named(syn::FieldsNamed{
-            named,..
-        }),..
-    }) = &st.data {
-        return Ok(named);
+// 与 CustomDebug 配套的 Display 派生宏，使用 #[display("{name} ({id})")] 这样的模板，
+// 模板写在结构体上或者枚举的各个变体上，字段遍历和泛型约束推断与 CustomDebug 共用
+#[proc_macro_derive(CustomDisplay,attributes(display))]
+pub fn derive_display(input: TokenStream) -> TokenStream {
+    let st = parse_macro_input!(input as DeriveInput);
+    match do_expand_display(&st) {
+        Ok(token_stream) => token_stream.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+// 取出所有的字段，对于枚举来说是所有变体的字段，推断泛型约束的时候需要用到
+fn get_fields_from_derive_input(st:&syn::DeriveInput) -> syn::Result<Vec<&syn::Field>> {
+    match &st.data {
+        syn::Data::Struct(syn::DataStruct{fields,..}) => Ok(fields.iter().collect()),
+        syn::Data::Enum(syn::DataEnum{variants,..}) => {
+            Ok(variants.iter().flat_map(|v| v.fields.iter()).collect())
+        }
+        syn::Data::Union(syn::DataUnion{fields,..}) => Ok(fields.named.iter().collect()),
     }
-    Err(syn::Error::new_spanned(st, "字段解析错误"))
 }
 
 fn generate_debug_fmt_body(st:&syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
-    let struct_name_ident = &st.ident;
-    let struct_name_literal = struct_name_ident.to_string();
-    let fields = get_fields_from_derive_input(st)?;
+    // 联合体无法知道当前哪个字段是有效的，读取字段也是 unsafe 的，所以只输出一个不透明的表示
+    if let syn::Data::Union(_) = st.data {
+        let name_literal = match get_name_value_of_attrs(&st.attrs, "name")? {
+            Some(name) => name.value(),
+            None => st.ident.to_string(),
+        };
+        return Ok(quote! {
+            fmt.debug_struct(#name_literal).finish_non_exhaustive()
+        });
+    }
+
+    generate_fmt_body(st, |name_ident, attrs, fields, accessors, _| {
+        // 结构体或者变体上的 #[debug(name = "...")] 可以修改输出的名字
+        let name_literal = match get_name_value_of_attrs(attrs, "name")? {
+            Some(name) => name.value(),
+            None => name_ident.to_string(),
+        };
+        generate_fields_fmt(&name_literal, fields, accessors)
+    })
+}
+
+// 依次处理结构体本身或者枚举的每个变体，f 接收名字、属性、字段、每个字段对应的引用表达式
+// 以及当前处理的是不是枚举的变体，返回这一部分的格式化代码。CustomDebug 和 CustomDisplay 共用这里的字段遍历逻辑
+fn generate_fmt_body<F>(st:&syn::DeriveInput, mut f:F) -> syn::Result<proc_macro2::TokenStream>
+where
+    F: FnMut(&syn::Ident, &[syn::Attribute], &syn::Fields, &[proc_macro2::TokenStream], bool) -> syn::Result<proc_macro2::TokenStream>,
+{
+    match &st.data {
+        syn::Data::Struct(syn::DataStruct{fields,..}) if is_packed_struct(st) => {
+            // #[repr(packed)] 的结构体中字段可能没有对齐，不能直接取引用，
+            // 需要在用到字段的地方先把字段复制出来，所以要求被格式化的字段实现了 Copy，
+            // #[debug(skip)] 等没有被格式化的字段不会被复制，也就不需要 Copy。
+            // 这里用一个带 Copy 约束的函数包一下，不满足时报错会指向对应的字段类型
+            let accessors = fields.iter().enumerate().map(|(idx, field)| {
+                let member = match &field.ident {
+                    Some(ident) => syn::Member::Named(ident.clone()),
+                    None => syn::Member::Unnamed(syn::Index::from(idx)),
+                };
+                quote_spanned! {field.ty.span()=>
+                    &{
+                        fn field_of_packed_struct_must_be_copy<T: std::marker::Copy>(value: T) -> T {
+                            value
+                        }
+                        field_of_packed_struct_must_be_copy(self.#member)
+                    }
+                }
+            }).collect::<Vec<_>>();
+            f(&st.ident, &st.attrs, fields, &accessors, false)
+        }
+        syn::Data::Struct(syn::DataStruct{fields,..}) => {
+            // 结构体直接通过 self.字段名 或 self.下标 访问字段
+            let accessors = fields.iter().enumerate().map(|(idx, field)| {
+                let member = match &field.ident {
+                    Some(ident) => syn::Member::Named(ident.clone()),
+                    None => syn::Member::Unnamed(syn::Index::from(idx)),
+                };
+                quote!(&self.#member)
+            }).collect::<Vec<_>>();
+            f(&st.ident, &st.attrs, fields, &accessors, false)
+        }
+        syn::Data::Enum(syn::DataEnum{variants,..}) => {
+            // 枚举需要先 match 出当前的变体，再把变体里的字段绑定到局部变量上进行格式化，
+            // 局部变量统一加上前缀，避免和 fmt 等名字冲突
+            let mut arms = Vec::new();
+            for variant in variants {
+                let variant_ident = &variant.ident;
+                let bindings = (0..variant.fields.len())
+                    .map(|idx| format_ident!("__self_{}", idx))
+                    .collect::<Vec<_>>();
+                let pattern = match &variant.fields {
+                    syn::Fields::Named(named) => {
+                        let idents = named.named.iter().map(|f| &f.ident);
+                        quote!(Self::#variant_ident { #(#idents: #bindings),* })
+                    }
+                    syn::Fields::Unnamed(_) => quote!(Self::#variant_ident(#(#bindings),*)),
+                    syn::Fields::Unit => quote!(Self::#variant_ident),
+                };
+                let accessors = bindings.iter().map(|b| quote!(#b)).collect::<Vec<_>>();
+                let body = f(variant_ident, &variant.attrs, &variant.fields, &accessors, true)?;
+                arms.push(quote! {
+                    #pattern => #body,
+                });
+            }
+            // 没有变体的枚举无法构造出值，对 *self 做空匹配即可
+            if arms.is_empty() {
+                return Ok(quote! {
+                    match *self {}
+                });
+            }
+            Ok(quote! {
+                match self {
+                    #(#arms)*
+                }
+            })
+        }
+        syn::Data::Union(_) => Err(syn::Error::new_spanned(
+            st,
+            "unions are not supported here",
+        )),
+    }
+}
 
+// 判断结构体是否带有 #[repr(packed)] 或者 #[repr(C, packed(N))] 这样的属性
+fn is_packed_struct(st:&syn::DeriveInput) -> bool {
+    st.attrs.iter().any(|attr| {
+        let mut packed = false;
+        if attr.path().is_ident("repr") {
+            let _ = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("packed") {
+                    packed = true;
+                }
+                skip_meta_value(&meta)
+            });
+        }
+        packed
+    })
+}
+
+// 为一组字段生成 debug_struct / debug_tuple 的调用链，accessors 中是每个字段对应的引用表达式
+fn generate_fields_fmt(
+    name_literal: &str,
+    fields: &syn::Fields,
+    accessors: &[proc_macro2::TokenStream],
+) -> syn::Result<proc_macro2::TokenStream> {
     let mut fmt_body_stream = proc_macro2::TokenStream::new();
-    fmt_body_stream.extend(quote! {
-        fmt.debug_struct(#struct_name_literal)
-    });
+    match fields {
+        syn::Fields::Named(_) => fmt_body_stream.extend(quote! {
+            fmt.debug_struct(#name_literal)
+        }),
+        syn::Fields::Unnamed(_) => fmt_body_stream.extend(quote! {
+            fmt.debug_tuple(#name_literal)
+        }),
+        syn::Fields::Unit => return Ok(quote! {
+            fmt.write_str(#name_literal)
+        }),
+    }
 
-    for field in fields {
-        let ident = field.ident.as_ref().unwrap();
-        let ident_literal = ident.to_string();
+    let mut has_skipped_field = false;
+    for (field, accessor) in fields.iter().zip(accessors) {
+        // #[debug(skip)] 的字段完全不输出，最后用 finish_non_exhaustive 提示还有省略的字段
+        if get_field_flag(field, "skip")? {
+            has_skipped_field = true;
+            continue;
+        }
+
+        let value = if let Some(show_len) = get_redact_of_fields(field)? {
+            // #[debug(redact)] 的字段只输出占位符，不暴露真实内容
+            if show_len {
+                quote!(format_args!("***(len: {})", (#accessor).len()))
+            } else {
+                quote!(format_args!("***"))
+            }
+        } else if let Some(with) = get_with_of_fields(field)? {
+            // #[debug(with = "path")] 的字段交给用户提供的函数格式化，函数签名为
+            // fn(&T, &mut fmt::Formatter) -> fmt::Result
+            generate_debug_with(quote!(#with(#accessor, fmt)))
+        } else if let (Some(format_str), true) = (
+            get_custom_format_of_fields(field)?,
+            get_radix_of_fields(field)?.is_some() && is_collection_type(&field.ty),
+        ) {
+            // #[debug(hex)] 等写在集合类型上时，对集合中的每个元素分别按进制输出
+            let element = generate_debug_with(quote!(write!(fmt, #format_str, value)));
+            generate_debug_with(quote! {
+                fmt.debug_list().entries((#accessor).iter().map(|value| #element)).finish()
+            })
+        } else if let Some(format_str) = get_custom_format_of_fields(field)? {
+            // 自定义格式中的`{:?}`在`{:#?}`模式下要换成`{:#?}`，否则嵌套的值不会换行缩进
+            let alternate_format_str = make_alternate_format(&format_str);
+            if alternate_format_str == format_str {
+                quote!(format_args!(#format_str,#accessor))
+            } else {
+                generate_debug_with(quote! {
+                    if fmt.alternate() {
+                        write!(fmt, #alternate_format_str, #accessor)
+                    } else {
+                        write!(fmt, #format_str, #accessor)
+                    }
+                })
+            }
+        } else {
+            // 没有自定义格式的字段直接交给 debug_struct，这样`{:#?}`的缩进格式才能正确传递下去
+            quote!(#accessor)
+        };
 
-        let mut format_str = "{:?}".to_string();
-        if let Some(format) = get_custom_format_of_fields(field)? {
-            format_str = format;
+        match &field.ident {
+            Some(ident) => {
+                // #[debug(rename = "...")] 可以修改输出的字段名
+                let ident_literal = match get_name_value_of_attrs(&field.attrs, "rename")? {
+                    Some(rename) => rename.value(),
+                    None => ident.to_string(),
+                };
+                fmt_body_stream.extend(quote! {
+                    .field(#ident_literal,&#value)
+                });
+            }
+            None => fmt_body_stream.extend(quote! {
+                .field(&#value)
+            }),
         }
+    }
+
+    if has_skipped_field {
+        fmt_body_stream.extend(quote! {
+            .finish_non_exhaustive()
+        });
+    } else {
         fmt_body_stream.extend(quote! {
-            .field(#ident_literal,&format_args!(#format_str,&self.#ident))
+            .finish()
         });
     }
-
-    fmt_body_stream.extend(quote! {
-        .finish()
-    });
     Ok(fmt_body_stream)
 }
 
+// 用一个实现了 Debug 的包装类型把一段格式化代码接到 .field(...) 上，代码中可以使用`fmt`访问 Formatter，
+// 闭包会 move 捕获代码中用到的变量，这样也可以用在 map 之类的闭包中
+fn generate_debug_with(body:proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    quote!({
+        struct DebugWith<F>(F);
+        impl<F: Fn(&mut std::fmt::Formatter) -> std::fmt::Result> std::fmt::Debug for DebugWith<F> {
+            fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+                (self.0)(fmt)
+            }
+        }
+        DebugWith(move |fmt: &mut std::fmt::Formatter| #body)
+    })
+}
+
+// 给格式字符串中所有的`{:?}`类占位符加上`#`标记，用于`{:#?}`模式，例如`{:?}`变成`{:#?}`，
+// `{:>8?}`变成`{:>#8?}`。其它类型的占位符保持不变，因为`{:#x}`这种写法会额外输出`0x`前缀
+fn make_alternate_format(format_str:&str) -> String {
+    let mut ret = String::new();
+    let mut chars = format_str.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            ret.push(c);
+            continue;
+        }
+        if chars.peek() == Some(&'{') {
+            chars.next();
+            ret.push_str("{{");
+            continue;
+        }
+        let mut inner = String::new();
+        for c in chars.by_ref() {
+            if c == '}' {
+                break;
+            }
+            inner.push(c);
+        }
+        if let Some((arg, spec)) = inner.split_once(':') {
+            if spec.ends_with('?') && !spec.contains('#') {
+                // 格式说明的顺序为 [[fill]align][sign]['#']['0'][width]['.' precision]type
+                let spec_chars = spec.chars().collect::<Vec<_>>();
+                let mut pos = if spec_chars.len() > 1 && "<^>".contains(spec_chars[1]) {
+                    2
+                } else if !spec_chars.is_empty() && "<^>".contains(spec_chars[0]) {
+                    1
+                } else {
+                    0
+                };
+                if pos < spec_chars.len() && "+-".contains(spec_chars[pos]) {
+                    pos += 1;
+                }
+                let byte_pos = spec_chars[..pos].iter().map(|c| c.len_utf8()).sum::<usize>();
+                inner = format!("{arg}:{}#{}", &spec[..byte_pos], &spec[byte_pos..]);
+            }
+        }
+        ret.push('{');
+        ret.push_str(&inner);
+        ret.push('}');
+    }
+    ret
+}
+
 fn generate_debug_trait(st:&syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream>{
     let struct_name_ident = &st.ident;
     let fmt_body_stream = generate_debug_fmt_body(st)?;
     let mut generic_param_to_modify = st.generics.clone();
 
-    if let Some(hatch) = get_struct_escape_hatch(st) {
-        generic_param_to_modify.make_where_clause();
-        generic_param_to_modify
-                    .where_clause
-                    .as_mut()
-                    .unwrap()
-                    .predicates
-                    .push(syn::parse_str(hatch.as_str()).unwrap());
-    }else {
+    // 结构体上的 #[debug(bound = "...")] 会完全替换掉自动推断出来的约束
+    let struct_bounds = get_struct_escape_hatch(st)?;
+    if !struct_bounds.is_empty() {
+        generic_param_to_modify.make_where_clause().predicates.extend(struct_bounds);
+    } else if let syn::Data::Union(_) = st.data {
+        // 联合体不输出任何字段，不需要约束
+    } else {
+        // packed 结构体的字段要先复制出来，字段中用到的泛型参数需要实现 Copy
+        // #[repr(packed)] 的结构体中被格式化的字段需要复制出来，#[debug(skip)] 的字段不需要
+        if is_packed_struct(st) {
+            let mut formatted_fields = Vec::new();
+            for field in get_fields_from_derive_input(st)? {
+                if !get_field_flag(field, "skip")? {
+                    formatted_fields.push(field);
+                }
+            }
+            add_inferred_bounds(&mut generic_param_to_modify, st, &formatted_fields, &parse_quote!(std::marker::Copy));
+        }
+
         let fields = get_fields_from_derive_input(st)?;
-        let mut field_type_names:Vec<String> = Vec::new();
-        let mut phantomdata_type_param_names:Vec<String> = Vec::new();
+        // 字段上的 #[debug(bound = "...")] 只替换根据这个字段推断出来的约束，其它字段照常推断
+        let mut inferable_fields = Vec::new();
         for field in fields {
-            if let Some(s) = get_field_type_name(field)? {
-                field_type_names.push(s);
+            let field_bounds = get_field_escape_hatch(field)?;
+            if !field_bounds.is_empty() {
+                generic_param_to_modify.make_where_clause().predicates.extend(field_bounds);
+                continue;
             }
-            if let Some(s) = get_phantomdata_generic_type_name(field)?{
-                phantomdata_type_param_names.push(s);
+            // 不通过 Debug 输出的字段不需要约束：skip 的字段不输出，redact 只输出占位符，
+            // with 交给用户提供的函数处理
+            if get_field_flag(field, "skip")?
+                || get_redact_of_fields(field)?.is_some()
+                || get_with_of_fields(field)?.is_some()
+            {
+                continue;
             }
-        }
-        
-        //找到关联类型信息
-        let associated_types_map = get_generic_associated_types(st);
-        // 取出范型定义，然后为每个范型追加 Debug 约束，之后重新插入到语法树中
-        for g in generic_param_to_modify.params.iter_mut() {
-            if let syn::GenericParam::Type(t) = g{
-                let type_param_name = t.ident.to_string();
-                // 如果是PhantomData，就不要对泛型参数`T`本身再添加约束了,除非`T`本身也被直接使用了
-                if phantomdata_type_param_names.contains(&type_param_name) && !field_type_names.contains(&type_param_name) {
-                    continue;
-                }
-
-                // 如果是关联类型，就不要对泛型参数`T`本身再添加约束了,除非`T`本身也被直接使用了
-                if associated_types_map.contains_key(&type_param_name) && !field_type_names.contains(&type_param_name) {
-                    continue;
-                }
-
-                // parse_quote! 将数据解析为语法树节点
-                t.bounds.push(parse_quote!(std::fmt::Debug));
+            // 按进制输出的字段需要的是 LowerHex、Binary 或 Octal，而不是 Debug
+            if let Some((spec, _)) = get_radix_of_fields(field)? {
+                let bound = get_format_trait(Some(&spec.to_string()));
+                add_inferred_bounds(&mut generic_param_to_modify, st, &[field], &bound);
+                continue;
             }
+            inferable_fields.push(field);
         }
 
-        // 关联类型的约束要放到where子句里
-        generic_param_to_modify.make_where_clause();
-        for (_, associated_types) in associated_types_map {
-            for associated_type in associated_types {
-                generic_param_to_modify.where_clause.as_mut().unwrap().predicates.push(parse_quote!(#associated_type:std::fmt::Debug));
-            }
-        }
+        add_inferred_bounds(&mut generic_param_to_modify, st, &inferable_fields, &parse_quote!(std::fmt::Debug));
     }
 
     // 使用工具函数 split_for_impl 将范型参数提取成三个片段，分别为 impl,type,where
@@ -120,10 +373,45 @@ fn generate_debug_trait(st:&syn::DeriveInput) -> syn::Result<proc_macro2::TokenS
             }
         }
     };
-    return Ok(ret);
+    Ok(ret)
 }
 
+// 为字段类型中直接用到的泛型参数和关联类型追加指定 trait 的约束
+fn add_inferred_bounds(generics:&mut syn::Generics, st:&syn::DeriveInput, fields:&[&syn::Field], bound:&syn::Path) {
+    // 找到字段类型中直接用到的泛型参数和关联类型
+    let (used_type_params, associated_types) = get_used_type_params(st, fields);
+    // 取出范型定义，只为字段中直接用到的泛型参数追加约束，之后重新插入到语法树中
+    // 没有用到的（例如只出现在PhantomData中，或者只以`T::Value`的形式出现）就不要添加了
+    for g in generics.params.iter_mut() {
+        if let syn::GenericParam::Type(t) = g{
+            if used_type_params.contains(&t.ident) {
+                // parse_quote! 将数据解析为语法树节点
+                t.bounds.push(parse_quote!(#bound));
+            }
+        }
+    }
+
+    // 关联类型的约束要放到where子句里
+    let where_clause = generics.make_where_clause();
+    for associated_type in associated_types {
+        where_clause.predicates.push(parse_quote!(#associated_type:#bound));
+    }
+}
+
+// 读取字段的自定义格式，#[debug(hex)] 这类进制简写会转换成对应的格式字符串
 fn get_custom_format_of_fields(field:&syn::Field) -> syn::Result<Option<String>> {
+    if let Some((spec, width)) = get_radix_of_fields(field)? {
+        let prefix = match spec {
+            'x' => "0x",
+            'b' => "0b",
+            _ => "0o",
+        };
+        // 指定宽度时用 0 补齐数字部分，宽度不包含前缀
+        return Ok(Some(match width {
+            Some(width) => format!("{prefix}{{:0{width}{spec}}}"),
+            None => format!("{{:#{spec}}}"),
+        }));
+    }
     for attr in &field.attrs{
         if let syn::Meta::NameValue(syn::MetaNameValue{
             ref path,
@@ -140,95 +428,540 @@ fn get_custom_format_of_fields(field:&syn::Field) -> syn::Result<Option<String>>
     Ok(None)
 }
 
-fn get_field_type_name(field:&syn::Field) -> syn::Result<Option<String>> {
-    if let syn::Type::Path(syn::TypePath{path: syn::Path{ref segments, ..}, ..}) = field.ty {
-        if let Some(syn::PathSegment{ref ident,..}) = segments.last() {
-            return Ok(Some(ident.to_string()))
+// 解析 #[debug(hex)]、#[debug(bin)]、#[debug(octal)] 以及带宽度的 #[debug(hex(8))]，
+// 返回格式说明中对应的类型字符和宽度
+fn get_radix_of_fields(field:&syn::Field) -> syn::Result<Option<(char, Option<usize>)>> {
+    let mut radix = None;
+    for attr in &field.attrs {
+        if attr.path().is_ident("debug") && matches!(attr.meta, syn::Meta::List(_)) {
+            attr.parse_nested_meta(|meta| {
+                let spec = if meta.path.is_ident("hex") {
+                    'x'
+                } else if meta.path.is_ident("bin") {
+                    'b'
+                } else if meta.path.is_ident("octal") {
+                    'o'
+                } else {
+                    return skip_meta_value(&meta);
+                };
+                let mut width = None;
+                if meta.input.peek(syn::token::Paren) {
+                    let content;
+                    syn::parenthesized!(content in meta.input);
+                    let lit:syn::LitInt = content.parse()?;
+                    width = Some(lit.base10_parse::<usize>()?);
+                }
+                radix = Some((spec, width));
+                Ok(())
+            })?;
         }
     }
-    return Ok(None)
+    Ok(radix)
 }
 
-fn get_phantomdata_generic_type_name(field:&syn::Field) -> syn::Result<Option<String>>{
-    if let syn::Type::Path(syn::TypePath{path: syn::Path{ref segments, ..}, ..}) = field.ty {
-        if let Some(syn::PathSegment{ref ident, ref arguments}) = segments.last() {
-            if ident == "PhantomData" {
-                if let syn::PathArguments::AngleBracketed(syn::AngleBracketedGenericArguments{args, ..}) = arguments {
-                    if let Some(syn::GenericArgument::Type(syn::Type::Path( ref gp))) = args.first() {
-                        if let Some(generic_ident) = gp.path.segments.first() {
-                            return Ok(Some(generic_ident.ident.to_string()))
-                        }
+// 判断字段是否是可以用`.iter()`遍历元素的集合类型，例如 Vec<T>、[T; N]、&[T]
+fn is_collection_type(ty:&syn::Type) -> bool {
+    match ty {
+        syn::Type::Array(_) | syn::Type::Slice(_) => true,
+        syn::Type::Reference(r) => is_collection_type(&r.elem),
+        syn::Type::Paren(p) => is_collection_type(&p.elem),
+        syn::Type::Path(syn::TypePath{path,..}) => path.segments.last().is_some_and(|seg| {
+            ["Vec", "VecDeque", "LinkedList", "HashSet", "BTreeSet", "BinaryHeap"]
+                .iter()
+                .any(|name| seg.ident == name)
+        }),
+        _ => false,
+    }
+}
+
+// 查找字段上形如 #[debug(skip)] 这种不带值的开关
+fn get_field_flag(field:&syn::Field, name:&str) -> syn::Result<bool> {
+    let mut flag = false;
+    for attr in &field.attrs {
+        if attr.path().is_ident("debug") && matches!(attr.meta, syn::Meta::List(_)) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident(name) {
+                    flag = true;
+                }
+                skip_meta_value(&meta)
+            })?;
+        }
+    }
+    Ok(flag)
+}
+
+// 解析 #[debug(redact)] 或 #[debug(redact(len))]，返回值表示是否需要输出长度
+fn get_redact_of_fields(field:&syn::Field) -> syn::Result<Option<bool>> {
+    let mut redact = None;
+    for attr in &field.attrs {
+        if attr.path().is_ident("debug") && matches!(attr.meta, syn::Meta::List(_)) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("redact") {
+                    let mut show_len = false;
+                    if meta.input.peek(syn::token::Paren) {
+                        meta.parse_nested_meta(|inner| {
+                            if inner.path.is_ident("len") {
+                                show_len = true;
+                                Ok(())
+                            } else {
+                                Err(inner.error("expected `debug(redact(len))`"))
+                            }
+                        })?;
                     }
+                    redact = Some(show_len);
+                    Ok(())
+                } else {
+                    skip_meta_value(&meta)
+                }
+            })?;
+        }
+    }
+    Ok(redact)
+}
+
+// 解析 #[debug(key = "...")] 形式的字符串参数，例如 #[debug(rename = "...")]
+fn get_name_value_of_attrs(attrs:&[syn::Attribute], name:&str) -> syn::Result<Option<LitStr>> {
+    let mut lit = None;
+    for attr in attrs {
+        if attr.path().is_ident("debug") && matches!(attr.meta, syn::Meta::List(_)) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident(name) {
+                    lit = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    skip_meta_value(&meta)
+                }
+            })?;
+        }
+    }
+    Ok(lit)
+}
+
+// 解析 #[debug(with = "path::to::fmt_fn")]，返回格式化函数的路径
+fn get_with_of_fields(field:&syn::Field) -> syn::Result<Option<syn::ExprPath>> {
+    let mut with = None;
+    for attr in &field.attrs {
+        if attr.path().is_ident("debug") && matches!(attr.meta, syn::Meta::List(_)) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("with") {
+                    let s:LitStr = meta.value()?.parse()?;
+                    with = Some(s.parse()?);
+                    Ok(())
+                } else {
+                    skip_meta_value(&meta)
                 }
+            })?;
+        }
+    }
+    Ok(with)
+}
+
+// 结构体上 #[debug(...)] 可以使用的键
+const STRUCT_ATTRIBUTE_KEYS: &[&str] = &["bound", "name"];
+// 枚举变体上 #[debug(...)] 可以使用的键
+const VARIANT_ATTRIBUTE_KEYS: &[&str] = &["name"];
+// 字段上 #[debug(...)] 可以使用的键
+const FIELD_ATTRIBUTE_KEYS: &[&str] = &["skip", "redact", "with", "bound", "rename", "hex", "bin", "octal"];
+
+// 在生成代码之前先检查所有的 #[debug(...)] 属性，把所有错误合并后一起报告
+fn check_debug_attributes(st:&syn::DeriveInput) -> syn::Result<()> {
+    let mut errors = Vec::new();
+    check_attribute_keys(&st.attrs, STRUCT_ATTRIBUTE_KEYS, &mut errors);
+    if let Err(err) = get_struct_escape_hatch(st) {
+        errors.push(err);
+    }
+    if let syn::Data::Enum(syn::DataEnum{variants,..}) = &st.data {
+        for variant in variants {
+            check_attribute_keys(&variant.attrs, VARIANT_ATTRIBUTE_KEYS, &mut errors);
+        }
+    }
+    for field in get_fields_from_derive_input(st)? {
+        check_attribute_keys(&field.attrs, FIELD_ATTRIBUTE_KEYS, &mut errors);
+        if let Err(err) = get_field_escape_hatch(field) {
+            errors.push(err);
+        }
+        // 元组字段输出时不带字段名，无法改名
+        match get_name_value_of_attrs(&field.attrs, "rename") {
+            Ok(Some(rename)) if field.ident.is_none() => errors.push(syn::Error::new_spanned(
+                rename,
+                "`debug(rename = \"...\")` can only be used on named fields",
+            )),
+            Err(err) => errors.push(err),
+            _ => {}
+        }
+        if let Err(err) = get_radix_of_fields(field) {
+            errors.push(err);
+        }
+        // skip、redact、with、进制简写和 #[debug = "..."] 都决定了字段如何输出，同时只能指定一种
+        let mut modes = Vec::new();
+        match get_field_flag(field, "skip") {
+            Ok(true) => modes.push("`debug(skip)`"),
+            Ok(false) => {}
+            Err(err) => errors.push(err),
+        }
+        match get_redact_of_fields(field) {
+            Ok(Some(_)) => modes.push("`debug(redact)`"),
+            Ok(None) => {}
+            Err(err) => errors.push(err),
+        }
+        match get_with_of_fields(field) {
+            Ok(Some(_)) => modes.push("`debug(with = ...)`"),
+            Ok(None) => {}
+            Err(err) => errors.push(err),
+        }
+        for radix in ["hex", "bin", "octal"] {
+            match get_field_flag(field, radix) {
+                Ok(true) => modes.push(match radix {
+                    "hex" => "`debug(hex)`",
+                    "bin" => "`debug(bin)`",
+                    _ => "`debug(octal)`",
+                }),
+                Ok(false) => {}
+                Err(err) => errors.push(err),
+            }
+        }
+        if field.attrs.iter().any(|a| a.path().is_ident("debug") && matches!(a.meta, syn::Meta::NameValue(_))) {
+            modes.push("`debug = \"...\"`");
+        }
+        if modes.len() > 1 {
+            errors.push(syn::Error::new_spanned(
+                field,
+                format!("{} cannot be used together", modes.join(" and ")),
+            ));
+        }
+    }
+
+    let mut errors = errors.into_iter();
+    match errors.next() {
+        Some(mut first) => {
+            for err in errors {
+                first.combine(err);
             }
+            Err(first)
         }
+        None => Ok(()),
     }
-    return Ok(None)
 }
 
-fn get_generic_associated_types(st:&syn::DeriveInput) -> HashMap<String,Vec<syn::TypePath>> {
-    // 构建筛选条件
-    let origin_generic_param_names = st.generics.params.iter().filter_map(|f| {
-        if let syn::GenericParam::Type(ty) = f {
-            return  Some(ty.ident.to_string());
+// 检查 #[debug(...)] 中的键是否都是认识的
+fn check_attribute_keys(attrs:&[syn::Attribute], known_keys:&[&str], errors:&mut Vec<syn::Error>) {
+    for attr in attrs {
+        if !attr.path().is_ident("debug") || !matches!(attr.meta, syn::Meta::List(_)) {
+            continue;
+        }
+        let ret = attr.parse_nested_meta(|meta| {
+            if !known_keys.iter().any(|k| meta.path.is_ident(k)) {
+                errors.push(syn::Error::new_spanned(
+                    &meta.path,
+                    format!("unknown debug attribute, expected one of: {}", known_keys.join(", ")),
+                ));
+            }
+            skip_meta_value(&meta)
+        });
+        if let Err(err) = ret {
+            errors.push(err);
+        }
+    }
+}
+
+// 跳过不关心的键后面的值，例如 `bound = "..."` 中的 `= "..."` 或 `redact(len)` 中的括号，
+// 这样 parse_nested_meta 才能继续解析后面的键
+fn skip_meta_value(meta:&syn::meta::ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        let value = meta.value()?;
+        while !value.is_empty() && !value.peek(syn::Token![,]) {
+            value.parse::<proc_macro2::TokenTree>()?;
         }
-        return None;
-    }).collect();
+    } else if meta.input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in meta.input);
+        content.parse::<proc_macro2::TokenStream>()?;
+    }
+    Ok(())
+}
 
-    let mut visitor = TypePathVisitor {
-        generic_type_names:origin_generic_param_names,
-        associated_types:HashMap::new()
-    };
+// 遍历字段类型，返回直接用到的泛型参数，以及用到的关联类型（例如`T::Value`）
+fn get_used_type_params(st:&syn::DeriveInput, fields:&[&syn::Field]) -> (HashSet<syn::Ident>, Vec<syn::TypePath>) {
+    // 构建筛选条件
+    let generic_type_params = st.generics.type_params().map(|t| t.ident.clone()).collect();
 
-    visitor.visit_derive_input(st);
-    return  visitor.associated_types;
+    let mut visitor = TypeParamVisitor {
+        generic_type_params,
+        used_type_params:HashSet::new(),
+        associated_types:Vec::new(),
+    };
 
+    for field in fields {
+        visitor.visit_type(&field.ty);
+    }
+    (visitor.used_type_params, visitor.associated_types)
 }
 
-fn get_struct_escape_hatch(st: &syn::DeriveInput) -> Option<String> {
-    if let Some(inert_attr) = st.attrs.last() {
-        if inert_attr.path().is_ident("debug") {
-            let mut lit= None;
-            let _ret = inert_attr.parse_nested_meta(|meta| {
+// 解析一组属性中所有的 #[debug(bound = "...")]，一个字符串里可以用逗号分隔写多个约束，
+// 也可以写多个属性，解析失败时错误会指向对应的字符串字面量
+fn get_bounds_from_attrs(attrs:&[syn::Attribute]) -> syn::Result<Vec<syn::WherePredicate>> {
+    let mut bounds = Vec::new();
+    for attr in attrs {
+        if attr.path().is_ident("debug") && matches!(attr.meta, syn::Meta::List(_)) {
+            attr.parse_nested_meta(|meta| {
                 if meta.path.is_ident("bound") {
-                    let value = meta.value()?;
-                    let s:LitStr = value.parse()?;
-                    lit = Some(s.value());
+                    let s:LitStr = meta.value()?.parse()?;
+                    let predicates = s.parse_with(
+                        syn::punctuated::Punctuated::<syn::WherePredicate, syn::Token![,]>::parse_terminated,
+                    )?;
+                    bounds.extend(predicates);
+                    Ok(())
+                } else {
+                    skip_meta_value(&meta)
                 }
-                Ok(())
-            });
-            return lit;
+            })?;
         }
     }
-    None
+    Ok(bounds)
+}
+
+fn get_struct_escape_hatch(st: &syn::DeriveInput) -> syn::Result<Vec<syn::WherePredicate>> {
+    get_bounds_from_attrs(&st.attrs)
+}
+
+fn get_field_escape_hatch(field:&syn::Field) -> syn::Result<Vec<syn::WherePredicate>> {
+    get_bounds_from_attrs(&field.attrs)
+}
+
+fn do_expand_display(st:&syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    if let syn::Data::Union(_) = st.data {
+        return Err(syn::Error::new_spanned(
+            st,
+            "`CustomDisplay` can only be derived for structs and enums",
+        ));
+    }
+    if let syn::Data::Enum(_) = st.data {
+        if let Some(attr) = st.attrs.iter().find(|a| a.path().is_ident("display")) {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "put `#[display(\"...\")]` on each variant of the enum instead",
+            ));
+        }
+    }
+
+    // 记录模板中引用到的字段以及需要的格式化 trait，用于推断泛型约束
+    let mut referenced_fields:Vec<(syn::Path, syn::Field)> = Vec::new();
+    let fmt_body_stream = generate_fmt_body(st, |name_ident, attrs, fields, accessors, is_variant| {
+        let template = match get_display_template(attrs)? {
+            Some(template) => template,
+            // 没有模板的单元变体直接输出变体名
+            None if matches!(fields, syn::Fields::Unit) && is_variant => {
+                let name_literal = name_ident.to_string();
+                return Ok(quote!(fmt.write_str(#name_literal)));
+            }
+            None => {
+                return Err(syn::Error::new_spanned(
+                    name_ident,
+                    "missing `#[display(\"...\")]` template",
+                ));
+            }
+        };
+
+        let (rewritten, placeholders) = parse_display_template(&template, fields)?;
+        let mut arg_indexes = Vec::new();
+        for (idx, bound) in placeholders {
+            if !arg_indexes.contains(&idx) {
+                arg_indexes.push(idx);
+            }
+            let field = fields.iter().nth(idx).unwrap();
+            referenced_fields.push((bound, field.clone()));
+        }
+        let arg_names = arg_indexes.iter().map(|idx| format_ident!("__field{}", idx));
+        let arg_values = arg_indexes.iter().map(|idx| &accessors[*idx]);
+        let rewritten = LitStr::new(&rewritten, template.span());
+        Ok(quote! {
+            write!(fmt, #rewritten #(, #arg_names = #arg_values)*)
+        })
+    })?;
+
+    // 按照需要的 trait 分组推断约束，例如`{value}`需要 Display，`{value:?}`需要 Debug
+    let mut generic_param_to_modify = st.generics.clone();
+    let mut bounds:Vec<&syn::Path> = Vec::new();
+    for (bound, _) in &referenced_fields {
+        if !bounds.contains(&bound) {
+            bounds.push(bound);
+        }
+    }
+    for bound in bounds {
+        let fields = referenced_fields
+            .iter()
+            .filter(|(b, _)| b == bound)
+            .map(|(_, f)| f)
+            .collect::<Vec<_>>();
+        add_inferred_bounds(&mut generic_param_to_modify, st, &fields, bound);
+    }
+
+    // #[repr(packed)] 的结构体中只有模板里用到的字段需要复制出来
+    if is_packed_struct(st) {
+        let mut formatted_fields:Vec<&syn::Field> = Vec::new();
+        for (_, field) in &referenced_fields {
+            if !formatted_fields.contains(&field) {
+                formatted_fields.push(field);
+            }
+        }
+        add_inferred_bounds(&mut generic_param_to_modify, st, &formatted_fields, &parse_quote!(std::marker::Copy));
+    }
+
+    let struct_name_ident = &st.ident;
+    let (impl_generic,type_generic,where_generic) = generic_param_to_modify.split_for_impl();
+    Ok(quote! {
+        impl #impl_generic std::fmt::Display for #struct_name_ident #type_generic #where_generic {
+            fn fmt(&self,fmt:&mut std::fmt::Formatter) -> std::fmt::Result {
+                #fmt_body_stream
+            }
+        }
+    })
+}
+
+// 读取结构体或者变体上的 #[display("...")] 模板
+fn get_display_template(attrs:&[syn::Attribute]) -> syn::Result<Option<LitStr>> {
+    for attr in attrs {
+        if attr.path().is_ident("display") {
+            return Ok(Some(attr.parse_args()?));
+        }
+    }
+    Ok(None)
+}
+
+// 解析模板中的占位符，占位符里写的是字段名（`{name}`）或者元组字段的下标（`{0}`），
+// 把它们统一改写成`__field下标`这样的具名参数，返回改写后的模板，以及每个占位符对应的字段下标和需要的 trait
+fn parse_display_template(template:&LitStr, fields:&syn::Fields) -> syn::Result<(String, Vec<(usize, syn::Path)>)> {
+    let value = template.value();
+    let mut chars = value.chars().peekable();
+    let mut rewritten = String::new();
+    let mut placeholders = Vec::new();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                rewritten.push_str("{{");
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                rewritten.push_str("}}");
+            }
+            '{' => {
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => inner.push(c),
+                        None => return Err(syn::Error::new_spanned(template, "unclosed `{` in display template")),
+                    }
+                }
+                let (arg, spec) = match inner.split_once(':') {
+                    Some((arg, spec)) => (arg.trim(), Some(spec)),
+                    None => (inner.trim(), None),
+                };
+                if arg.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        template,
+                        "placeholders in display templates need a field name or index, like `{name}` or `{0}`",
+                    ));
+                }
+                let idx = fields.iter().enumerate().position(|(idx, field)| match &field.ident {
+                    Some(ident) => ident == arg,
+                    None => arg == idx.to_string(),
+                }).ok_or_else(|| syn::Error::new_spanned(
+                    template,
+                    format!("no field `{arg}` to use in display template"),
+                ))?;
+
+                rewritten.push_str(&format!("{{__field{idx}"));
+                if let Some(spec) = spec {
+                    rewritten.push(':');
+                    rewritten.push_str(spec);
+                }
+                rewritten.push('}');
+                placeholders.push((idx, get_format_trait(spec)));
+            }
+            '}' => return Err(syn::Error::new_spanned(template, "unmatched `}` in display template")),
+            c => rewritten.push(c),
+        }
+    }
+    Ok((rewritten, placeholders))
+}
+
+// 根据占位符中的格式说明判断需要哪个格式化 trait，例如`{:?}`需要 Debug，`{:x}`需要 LowerHex
+fn get_format_trait(spec:Option<&str>) -> syn::Path {
+    match spec.and_then(|s| s.chars().last()) {
+        Some('?') => parse_quote!(std::fmt::Debug),
+        Some('x') => parse_quote!(std::fmt::LowerHex),
+        Some('X') => parse_quote!(std::fmt::UpperHex),
+        Some('o') => parse_quote!(std::fmt::Octal),
+        Some('b') => parse_quote!(std::fmt::Binary),
+        Some('e') => parse_quote!(std::fmt::LowerExp),
+        Some('E') => parse_quote!(std::fmt::UpperExp),
+        _ => parse_quote!(std::fmt::Display),
+    }
 }
 
 fn do_expand(st:&syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    check_debug_attributes(st)?;
     let debug_trait_tokenstream = generate_debug_trait(st)?;
-    return Ok(debug_trait_tokenstream);
+    Ok(debug_trait_tokenstream)
 }
 
 
 // 使用 syn 的 visit 模式可以实现当遍历语法树到指定的节点的时候触发回调
 // 需要在 features 中开启 visit 才可以使用
-struct TypePathVisitor {
-    // 这个是筛选条件，里面记录了所有的泛型参数的名字，例如`T`,`U`等
-    generic_type_names:Vec<String>,
-    // 这里记录了所有满足条件的语法树节点
-    associated_types: HashMap<String, Vec<syn::TypePath>>,  
+// 这里的推断方式和 serde 一样：约束加在用到的泛型参数和关联类型上，而不是整个字段类型上，
+// 否则像`Option<Box<Two<T>>>: Debug`这样的约束在递归类型中会导致编译器无限展开
+struct TypeParamVisitor {
+    // 这个是筛选条件，里面记录了所有的类型泛型参数，例如`T`,`U`等，
+    // 生命周期参数和常量泛型参数不需要 Debug 约束，所以不在这里面
+    generic_type_params: HashSet<syn::Ident>,
+    // 在字段类型中直接用到的泛型参数，例如`Vec<T>`、`&'a T`、`(T, U)`中的`T`和`U`
+    used_type_params: HashSet<syn::Ident>,
+    // 在字段类型中用到的关联类型，例如`T::Value`
+    associated_types: Vec<syn::TypePath>,
 }
 
-impl<'ast> Visit<'ast> for TypePathVisitor {
+impl<'ast> Visit<'ast> for TypeParamVisitor {
     fn visit_type_path(&mut self, node: &'ast syn::TypePath) {
-        // 只对 T::Value1 这种类型进行处理
-        if node.path.segments.len() >= 2 {
-            let generic_type_name = node.path.segments[0].ident.to_string();
-            if self.generic_type_names.contains(&generic_type_name) {
-                self.associated_types.entry(generic_type_name).or_insert(Vec::new()).push(node.clone());
+        // `<T as Trait>::Value`这种带 QSelf 的写法也是关联类型，只要 QSelf 中用到了泛型参数，
+        // 就把整个类型作为约束的对象，而不是给`T`本身加约束，也不需要再往里面找了
+        if let Some(qself) = &node.qself {
+            let mut qself_visitor = TypeParamVisitor {
+                generic_type_params:self.generic_type_params.clone(),
+                used_type_params:HashSet::new(),
+                associated_types:Vec::new(),
+            };
+            qself_visitor.visit_type(&qself.ty);
+            if !qself_visitor.used_type_params.is_empty() || !qself_visitor.associated_types.is_empty() {
+                if !self.associated_types.contains(node) {
+                    self.associated_types.push(node.clone());
+                }
+                return;
+            }
+        }
+
+        if node.qself.is_none() && node.path.leading_colon.is_none() {
+            if let Some(first) = node.path.segments.first() {
+                if self.generic_type_params.contains(&first.ident) {
+                    if node.path.segments.len() == 1 {
+                        self.used_type_params.insert(first.ident.clone());
+                    } else if !self.associated_types.contains(node) {
+                        self.associated_types.push(node.clone());
+                    }
+                }
             }
         }
+
+        // PhantomData<T> 对任何`T`都实现了 Debug，包括`PhantomData<fn() -> T>`这种写法，
+        // 所以不需要再往里面找了
+        if node.path.segments.last().is_some_and(|seg| seg.ident == "PhantomData") {
+            return;
+        }
+
         // Visit 模式要求在当前节点访问完成后，继续调用默认实现的visit方法，从而遍历到所有的
         // 必须调用这个函数，否则遍历到这个节点就不再往更深层走了
         visit::visit_type_path(self, node);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,44 @@
+// CustomDebug also works on enums, tuple structs and unit structs, producing
+// the same output as the standard library's derive(Debug): debug_tuple for
+// tuple structs and tuple variants, debug_struct for struct variants, and the
+// bare name for unit structs and unit variants. Per-field #[debug = "..."]
+// formats are honoured everywhere.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct Point(i32, #[debug = "0x{:02x}"] u8);
+
+#[derive(CustomDebug)]
+pub struct Marker;
+
+#[derive(CustomDebug)]
+pub enum Event<T> {
+    Start,
+    Key(char, #[debug = "0b{:04b}"] u8),
+    Move {
+        x: i32,
+        #[debug = "{}px"]
+        y: i32,
+    },
+    Custom(T),
+}
+
+#[derive(CustomDebug)]
+pub enum Never {}
+
+fn main() {
+    assert_eq!(format!("{:?}", Point(-1, 10)), "Point(-1, 0x0a)");
+    assert_eq!(format!("{:?}", Marker), "Marker");
+
+    assert_eq!(format!("{:?}", Event::<()>::Start), "Start");
+    assert_eq!(format!("{:?}", Event::<()>::Key('a', 5)), "Key('a', 0b0101)");
+    assert_eq!(
+        format!("{:?}", Event::<()>::Move { x: 1, y: 2 }),
+        "Move { x: 1, y: 2px }",
+    );
+    assert_eq!(format!("{:?}", Event::Custom("s")), r#"Custom("s")"#);
+
+    fn assert_debug<F: std::fmt::Debug>() {}
+    assert_debug::<Never>();
+}
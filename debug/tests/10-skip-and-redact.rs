@@ -0,0 +1,36 @@
+// Structs that end up in logs often hold secrets. A field marked
+// #[debug(skip)] is left out of the output entirely, in which case the output
+// ends with `..` (finish_non_exhaustive) to show that something was omitted. A
+// field marked #[debug(redact)] is printed as `***`, and #[debug(redact(len))]
+// additionally shows the length of the hidden value.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct Credentials {
+    user: &'static str,
+    #[debug(redact)]
+    password: String,
+    #[debug(redact(len))]
+    token: String,
+    #[debug(skip)]
+    cache: Vec<u8>,
+}
+
+#[derive(CustomDebug)]
+pub struct Key(u32, #[debug(skip)] [u8; 4]);
+
+fn main() {
+    let c = Credentials {
+        user: "root",
+        password: "hunter2".to_owned(),
+        token: "abcdef".to_owned(),
+        cache: vec![1, 2, 3],
+    };
+
+    let debug = format!("{:?}", c);
+    let expected = r#"Credentials { user: "root", password: ***, token: ***(len: 6), .. }"#;
+    assert_eq!(debug, expected);
+
+    assert_eq!(format!("{:?}", Key(7, [0; 4])), "Key(7, ..)");
+}
@@ -0,0 +1,21 @@
+// Unrecognized keys inside #[debug(...)] on a field are reported at the key
+// itself. Keys that cannot be combined, such as skip and redact on the same
+// field, are rejected too, as are malformed values such as `redact(length)`.
+// Every such mistake is reported in one go.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct Credentials {
+    #[debug(hide)]
+    password: String,
+    #[debug(redact, skip)]
+    token: String,
+    #[debug(with = "fmt_token")]
+    #[debug = "{:?}"]
+    session: String,
+    #[debug(redact(length))]
+    pin: u32,
+}
+
+fn main() {}
@@ -0,0 +1,56 @@
+// Format strings only cover simple cases. A field marked
+// #[debug(with = "path::to::function")] is formatted by calling the given
+// function, which has the signature
+//
+//     fn(&T, &mut fmt::Formatter) -> fmt::Result
+//
+// where T is the type of the field. This makes it possible to print byte
+// buffers as hex dumps, truncate long strings, and so on.
+
+use derive_debug::CustomDebug;
+use std::fmt;
+
+mod fmt_helpers {
+    use std::fmt;
+
+    pub fn hex(bytes: &[u8], f: &mut fmt::Formatter) -> fmt::Result {
+        for b in bytes {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+fn truncated(s: &String, f: &mut fmt::Formatter) -> fmt::Result {
+    if s.len() > 5 {
+        write!(f, "{:?}...", &s[..5])
+    } else {
+        write!(f, "{:?}", s)
+    }
+}
+
+#[derive(CustomDebug)]
+pub struct Packet {
+    #[debug(with = "fmt_helpers::hex")]
+    payload: Vec<u8>,
+    #[debug(with = "truncated")]
+    note: String,
+}
+
+#[derive(CustomDebug)]
+pub enum Frame {
+    Data(#[debug(with = "fmt_helpers::hex")] Vec<u8>),
+}
+
+fn main() {
+    let p = Packet {
+        payload: vec![0xde, 0xad, 0xbe, 0xef],
+        note: "hello world".to_owned(),
+    };
+
+    let debug = format!("{:?}", p);
+    let expected = r#"Packet { payload: deadbeef, note: "hello"... }"#;
+    assert_eq!(debug, expected);
+
+    assert_eq!(format!("{:?}", Frame::Data(vec![1, 2])), "Data(0102)");
+}
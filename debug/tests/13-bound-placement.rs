@@ -0,0 +1,59 @@
+// The #[debug(bound = "...")] escape hatch is found wherever it appears among
+// the struct's attributes, not only when it is the last one. Several bounds
+// can be given, either comma-separated in one string or across attributes.
+//
+// The attribute can also be placed on an individual field. This replaces only
+// the bounds that would have been inferred from that field's type, while the
+// bounds inferred from the other fields are kept.
+
+use derive_debug::CustomDebug;
+use std::fmt::Debug;
+
+pub trait Trait {
+    type Value;
+}
+
+#[derive(CustomDebug)]
+struct Field<T: Trait> {
+    values: Vec<T::Value>,
+}
+
+/// Doc comments and other attributes may come after the escape hatch.
+#[derive(CustomDebug)]
+#[debug(bound = "T::Value: Debug, U::Value: Debug")]
+#[debug(bound = "V: Debug")]
+#[repr(C)]
+pub struct Pair<T: Trait, U: Trait, V> {
+    left: Field<T>,
+    right: Field<U>,
+    extra: V,
+}
+
+#[derive(CustomDebug)]
+pub struct Wrapper<T: Trait, U> {
+    #[debug(bound = "T::Value: Debug")]
+    field: Field<T>,
+    normal: U,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    struct Id;
+
+    impl Trait for Id {
+        type Value = u8;
+    }
+
+    assert_debug::<Pair<Id, Id, String>>();
+    assert_debug::<Wrapper<Id, String>>();
+
+    let w = Wrapper::<Id, &str> {
+        field: Field { values: vec![1] },
+        normal: "n",
+    };
+    assert_eq!(
+        format!("{:?}", w),
+        r#"Wrapper { field: Field { values: [1] }, normal: "n" }"#,
+    );
+}
@@ -0,0 +1,13 @@
+// A bound that does not parse as a where-clause predicate is reported as an
+// error pointing at the string literal, instead of panicking inside the macro.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+#[debug(bound = "T Debug")]
+pub struct Wrapper<T> {
+    #[debug(bound = "T::: Debug")]
+    field: T,
+}
+
+fn main() {}
@@ -0,0 +1,62 @@
+// Debug bounds are inferred from how each type parameter is used in the field
+// types, wherever it appears: inside Vec<T>, Option<Box<T>>, tuples or
+// references. Parameters that only appear inside PhantomData (in any form,
+// including PhantomData<fn() -> T>) or only in fields that are not printed
+// through Debug do not get a bound, and associated types such as T::Value are
+// bounded on their own.
+
+use derive_debug::CustomDebug;
+use std::fmt::{self, Debug};
+use std::marker::PhantomData;
+
+pub trait Trait {
+    type Value;
+}
+
+fn opaque<T>(_: &T, f: &mut fmt::Formatter) -> fmt::Result {
+    f.write_str("opaque")
+}
+
+#[derive(CustomDebug)]
+pub struct Inference<'a, A, B, C, D, E, F, G: Trait> {
+    list: Vec<A>,
+    nested: Option<Box<B>>,
+    pair: (A, C),
+    borrowed: &'a C,
+    marker: PhantomData<fn() -> D>,
+    #[debug(skip)]
+    hidden: E,
+    #[debug(with = "opaque")]
+    custom: F,
+    values: Vec<G::Value>,
+}
+
+fn assert_debug<T: Debug>() {}
+
+fn main() {
+    struct NotDebug;
+
+    struct Id;
+
+    impl Trait for Id {
+        type Value = u8;
+    }
+
+    assert_debug::<Inference<u8, String, bool, NotDebug, NotDebug, NotDebug, Id>>();
+
+    let value = Inference::<u8, u16, char, NotDebug, NotDebug, NotDebug, Id> {
+        list: vec![1],
+        nested: Some(Box::new(2)),
+        pair: (3, 'x'),
+        borrowed: &'y',
+        marker: PhantomData,
+        hidden: NotDebug,
+        custom: NotDebug,
+        values: vec![4],
+    };
+    let debug = format!("{:?}", value);
+    assert!(debug.starts_with(
+        "Inference { list: [1], nested: Some(2), pair: (3, 'x'), borrowed: 'y', marker: PhantomData<",
+    ));
+    assert!(debug.ends_with("NotDebug>, custom: opaque, values: [4], .. }"));
+}
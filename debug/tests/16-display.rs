@@ -0,0 +1,68 @@
+// The crate also provides #[derive(CustomDisplay)], driven by a
+// #[display("...")] template on the struct or on each enum variant.
+// Placeholders name a field (`{name}`) or the index of a tuple field (`{0}`)
+// and may carry the usual format specs (`{id:04}`, `{value:?}`, `{bits:x}`).
+// Unit variants without a template display their name, even when the
+// variant has the same name as the enum.
+//
+// Bounds on type parameters are inferred the same way as for CustomDebug,
+// using the trait each placeholder needs: `{value}` requires Display while
+// `{value:?}` requires Debug. Fields not mentioned in the template get none.
+
+use derive_debug::CustomDisplay;
+use std::fmt::Display;
+
+#[derive(CustomDisplay)]
+#[display("{name} ({id:04})")]
+pub struct User {
+    id: u32,
+    name: String,
+}
+
+#[derive(CustomDisplay)]
+#[display("({0}, {1})")]
+pub struct Point(i32, i32);
+
+#[derive(CustomDisplay)]
+pub enum Shape<T, U> {
+    #[display("circle of radius {0}")]
+    Circle(T),
+    #[display("rect {w}x{h} tagged {tag:?} {{raw}}")]
+    Rect { w: u32, h: u32, tag: U },
+    #[display("flags 0x{bits:x}")]
+    Flags { bits: u8, ignored: U },
+    Empty,
+}
+
+#[derive(CustomDisplay)]
+pub enum State {
+    State,
+    #[display("other {0}")]
+    Other(u8),
+}
+
+fn assert_display<F: Display>() {}
+
+fn main() {
+    let user = User {
+        id: 7,
+        name: "ferris".to_owned(),
+    };
+    assert_eq!(user.to_string(), "ferris (0007)");
+    assert_eq!(Point(1, -2).to_string(), "(1, -2)");
+
+    type S = Shape<f64, &'static str>;
+    assert_eq!(S::Circle(1.5).to_string(), "circle of radius 1.5");
+    assert_eq!(
+        S::Rect { w: 2, h: 3, tag: "a" }.to_string(),
+        r#"rect 2x3 tagged "a" {raw}"#,
+    );
+    assert_eq!(S::Flags { bits: 255, ignored: "" }.to_string(), "flags 0xff");
+    assert_eq!(S::Empty.to_string(), "Empty");
+
+    assert_eq!(State::State.to_string(), "State");
+    assert_eq!(State::Other(3).to_string(), "other 3");
+
+    // U is only formatted with {:?}, so it needs Debug but not Display.
+    assert_display::<Shape<u8, Vec<u8>>>();
+}
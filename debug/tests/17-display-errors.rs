@@ -0,0 +1,23 @@
+// Mistakes in #[derive(CustomDisplay)] templates are reported at the template
+// or at the item that is missing one.
+
+use derive_debug::CustomDisplay;
+
+#[derive(CustomDisplay)]
+#[display("{name} ({age})")]
+pub struct User {
+    name: String,
+}
+
+#[derive(CustomDisplay)]
+pub enum Shape {
+    #[display("circle {}")]
+    Circle(f64),
+}
+
+#[derive(CustomDisplay)]
+pub struct Missing {
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,62 @@
+// Pretty printing with {:#?} reaches through fields formatted by CustomDebug:
+// fields without a custom format are handed to the formatter as-is, and the
+// `{:?}` placeholders of a custom #[debug = "..."] format switch to `{:#?}`
+// when the formatter is in alternate mode.
+//
+// Field labels can be changed with #[debug(rename = "...")], and the name
+// printed for a struct or enum variant with #[debug(name = "...")].
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct Inner {
+    a: u8,
+}
+
+#[derive(CustomDebug)]
+#[debug(name = "Cfg")]
+pub struct Config {
+    #[debug(rename = "user")]
+    name: String,
+    #[debug = "<{:?}>"]
+    wrapped: Inner,
+    plain: Inner,
+}
+
+#[derive(CustomDebug)]
+pub enum Message {
+    #[debug(name = "Msg")]
+    Text {
+        #[debug(rename = "body")]
+        text: &'static str,
+    },
+}
+
+fn main() {
+    let config = Config {
+        name: "ferris".to_owned(),
+        wrapped: Inner { a: 1 },
+        plain: Inner { a: 2 },
+    };
+
+    assert_eq!(
+        format!("{:?}", config),
+        r#"Cfg { user: "ferris", wrapped: <Inner { a: 1 }>, plain: Inner { a: 2 } }"#,
+    );
+
+    let expected = r#"Cfg {
+    user: "ferris",
+    wrapped: <Inner {
+        a: 1,
+    }>,
+    plain: Inner {
+        a: 2,
+    },
+}"#;
+    assert_eq!(format!("{:#?}", config), expected);
+
+    assert_eq!(
+        format!("{:?}", Message::Text { text: "hi" }),
+        r#"Msg { body: "hi" }"#,
+    );
+}
@@ -0,0 +1,8 @@
+// Tuple fields are printed without labels, so renaming one is an error.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+pub struct Point(#[debug(rename = "x")] i32, i32);
+
+fn main() {}
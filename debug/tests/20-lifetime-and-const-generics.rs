@@ -0,0 +1,42 @@
+// Lifetime and const parameters pass through to the generated impl unchanged,
+// and only type parameters receive inferred bounds. Const parameters used as
+// array lengths as in [T; N] bound only the element type, references such as
+// &'a T bound the referenced type, and associated types written with a
+// qualified self type like <T as Trait>::Value are bounded as a whole instead
+// of requiring Debug on T.
+
+use derive_debug::CustomDebug;
+use std::fmt::Debug;
+
+pub trait Trait {
+    type Value;
+}
+
+#[derive(CustomDebug)]
+pub struct Mixed<'a, T: Trait, U, const N: usize> {
+    array: [U; N],
+    borrowed: &'a U,
+    qualified: Vec<<T as Trait>::Value>,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    struct Id;
+
+    impl Trait for Id {
+        type Value = u8;
+    }
+
+    assert_debug::<Mixed<'static, Id, i32, 3>>();
+
+    let value = Mixed::<Id, char, 2> {
+        array: ['a', 'b'],
+        borrowed: &'c',
+        qualified: vec![1, 2],
+    };
+    assert_eq!(
+        format!("{:?}", value),
+        "Mixed { array: ['a', 'b'], borrowed: 'c', qualified: [1, 2] }",
+    );
+}
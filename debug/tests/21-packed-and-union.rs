@@ -0,0 +1,49 @@
+// Fields of a #[repr(packed)] struct may be unaligned, and taking a reference
+// to them is an error. CustomDebug copies each field out of the struct before
+// formatting it, which requires the field types to be Copy.
+//
+// Unions print an opaque `Name { .. }`, because there is no way to know which
+// field is active.
+
+use derive_debug::{CustomDebug, CustomDisplay};
+use std::fmt::Debug;
+
+#[derive(CustomDebug, CustomDisplay)]
+#[repr(C, packed)]
+#[display("{tag}:{value}")]
+pub struct Header<T> {
+    tag: u8,
+    #[debug = "0x{:08x}"]
+    value: u32,
+    extra: T,
+}
+
+#[derive(CustomDebug)]
+#[repr(packed(2))]
+pub struct Pair(u8, u64);
+
+#[derive(CustomDebug)]
+pub union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn assert_debug<F: Debug>() {}
+
+fn main() {
+    let header = Header {
+        tag: 1,
+        value: 0xbeef,
+        extra: 'x',
+    };
+    assert_eq!(
+        format!("{:?}", header),
+        "Header { tag: 1, value: 0x0000beef, extra: 'x' }",
+    );
+    assert_eq!(header.to_string(), "1:48879");
+
+    assert_eq!(format!("{:?}", Pair(1, 2)), "Pair(1, 2)");
+
+    assert_debug::<Bits>();
+    assert_eq!(format!("{:?}", Bits { int: 1 }), "Bits { .. }");
+}
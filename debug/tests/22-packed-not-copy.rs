@@ -0,0 +1,13 @@
+// A packed struct with a field that is not Copy cannot be formatted, and the
+// error points at the type of the offending field.
+
+use derive_debug::CustomDebug;
+
+#[derive(CustomDebug)]
+#[repr(packed)]
+pub struct Packet {
+    len: u16,
+    payload: String,
+}
+
+fn main() {}
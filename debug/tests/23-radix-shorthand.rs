@@ -0,0 +1,61 @@
+// Addresses and flags are easier to read in hex or binary. Instead of writing
+// out a format string, a field can be marked #[debug(hex)], #[debug(bin)] or
+// #[debug(octal)], optionally with a width for the digits:
+//
+//     #[debug(hex)]     =>  0xbeef
+//     #[debug(hex(8))]  =>  0x0000beef
+//     #[debug(bin(4))]  =>  0b0101
+//
+// On collections such as Vec<T>, arrays and slices every element is formatted
+// on its own, and the generic parameters only need the matching formatting
+// trait (LowerHex, Binary or Octal) rather than Debug.
+
+use derive_debug::CustomDebug;
+use std::fmt::LowerHex;
+
+#[derive(CustomDebug)]
+pub struct Registers<'a, T> {
+    #[debug(hex(8))]
+    pc: u32,
+    #[debug(hex)]
+    sp: u16,
+    #[debug(bin(4))]
+    flags: u8,
+    #[debug(octal)]
+    mode: u16,
+    #[debug(hex(2))]
+    bytes: Vec<u8>,
+    #[debug(hex)]
+    words: [T; 2],
+    #[debug(bin)]
+    mask: &'a [u8],
+}
+
+struct Word(u16);
+
+impl LowerHex for Word {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        LowerHex::fmt(&self.0, f)
+    }
+}
+
+fn main() {
+    let regs = Registers {
+        pc: 0xbeef,
+        sp: 0x7ff0,
+        flags: 0b0101,
+        mode: 0o755,
+        bytes: vec![0xde, 0xad, 0x1],
+        words: [Word(0x10), Word(0xff)],
+        mask: &[1, 2],
+    };
+
+    assert_eq!(
+        format!("{:?}", regs),
+        "Registers { pc: 0x0000beef, sp: 0x7ff0, flags: 0b0101, mode: 0o755, \
+         bytes: [0xde, 0xad, 0x01], words: [0x10, 0xff], mask: [0b1, 0b10] }",
+    );
+
+    let pretty = format!("{:#?}", regs);
+    assert!(pretty.contains("    bytes: [\n        0xde,\n        0xad,\n        0x01,\n    ],\n"));
+}
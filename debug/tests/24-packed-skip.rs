@@ -0,0 +1,23 @@
+// Only the fields of a #[repr(packed)] struct that are actually formatted are
+// copied out of it, so fields skipped with #[debug(skip)], or not mentioned
+// in the #[display("...")] template, do not need to be Copy.
+
+use derive_debug::{CustomDebug, CustomDisplay};
+
+#[derive(CustomDebug, CustomDisplay)]
+#[repr(packed)]
+#[display("a = {a}")]
+pub struct P {
+    a: u32,
+    #[debug(skip)]
+    b: String,
+}
+
+fn main() {
+    let p = P {
+        a: 7,
+        b: String::from("hidden"),
+    };
+    assert_eq!(format!("{:?}", p), "P { a: 7, .. }");
+    assert_eq!(p.to_string(), "a = 7");
+}
@@ -9,4 +9,20 @@ fn tests() {
     t.pass("tests/06-bound-trouble.rs");
     t.pass("tests/07-associated-type.rs");
     t.pass("tests/08-escape-hatch.rs");
+    t.pass("tests/09-enums-and-tuples.rs");
+    t.pass("tests/10-skip-and-redact.rs");
+    t.compile_fail("tests/11-unknown-field-attribute.rs");
+    t.pass("tests/12-custom-formatter.rs");
+    t.pass("tests/13-bound-placement.rs");
+    t.compile_fail("tests/14-malformed-bound.rs");
+    t.pass("tests/15-bound-inference.rs");
+    t.pass("tests/16-display.rs");
+    t.compile_fail("tests/17-display-errors.rs");
+    t.pass("tests/18-alternate-and-rename.rs");
+    t.compile_fail("tests/19-rename-tuple-field.rs");
+    t.pass("tests/20-lifetime-and-const-generics.rs");
+    t.pass("tests/21-packed-and-union.rs");
+    t.compile_fail("tests/22-packed-not-copy.rs");
+    t.pass("tests/23-radix-shorthand.rs");
+    t.pass("tests/24-packed-skip.rs");
 }
@@ -7,45 +7,424 @@ pub fn seq(input: TokenStream) -> TokenStream {
 
     let mut ret = proc_macro2::TokenStream::new();
 
-    // 从TokenStream创建TokenBuffer
+    // 从TokenStream创建TokenBuffer，整个展开过程都在这一个TokenBuffer上用Cursor完成，
+    // 每次迭代不需要重新收集循环体中的Token
     let buffer = syn::buffer::TokenBuffer::new2(st.body.clone());
+    let unbound = vec![None; st.variables.len()];
 
-    // 首先尝试寻找`#(xxxxxxxxx)*`模式的代码块
-    let(ret_1, expanded) =  st.find_block_to_expand_and_do_expand(buffer.begin());
-    if expanded {
-        return ret_1.into()
+    // 首先寻找`#(xxxxxxxxx)*`模式的代码块，找到了就只展开这些代码块
+    if has_section(buffer.begin()) {
+        match st.expand(buffer.begin(), &unbound, true) {
+            Ok(t) => ret.extend(t),
+            Err(err) => return err.to_compile_error().into(),
+        }
+        return st.with_size_warning(ret).into()
     }
 
     // 走到这里，说明`#(xxxxxxxxx)*`这个模式没有匹配到，那么重新使用上一关的方式，在整个代码块中尝试展开
-    for i in st.start..st.end {
-        ret.extend(st.expand(&st.body, i))
+    let all_variables = (0..st.variables.len()).collect::<Vec<_>>();
+    for positions in st.iterations(&all_variables, &unbound) {
+        match st.expand(buffer.begin(), &positions, false) {
+            Ok(t) => ret.extend(t),
+            Err(err) => return err.to_compile_error().into(),
+        }
     }
 
-    return ret.into();
+    st.with_size_warning(ret).into()
+}
+
+// 判断展开结果的开头是不是只能出现在模块或者函数体中的item或者`let`语句，只有这时才能在前面插入`const _`。
+// `fn`、`const`、`type`、`static`以及属性、`pub`开头的代码也可能出现在impl、trait或者extern块中，
+// 那里不允许`const _`；展开结果是表达式、类型、模式等其它情况时同样不能插入，这些情况都不给出警告
+fn starts_with_item_or_statement(ts: &proc_macro2::TokenStream) -> bool {
+    let mut iter = ts.clone().into_iter();
+    let (first, second) = (iter.next(), iter.next());
+    let next_is = |keywords: &[&str]| matches!(&second, Some(proc_macro2::TokenTree::Ident(ident)) if keywords.iter().any(|k| ident == k));
+    let Some(proc_macro2::TokenTree::Ident(ident)) = &first else {
+        return false;
+    };
+    match ident.to_string().as_str() {
+        "struct" | "enum" | "union" | "mod" | "use" | "impl" | "trait" | "let" => true,
+        "extern" => next_is(&["crate"]),
+        // `unsafe fn`可以出现在impl中，`unsafe { ... }`是表达式
+        "unsafe" => next_is(&["impl", "trait"]),
+        _ => false,
+    }
+}
+
+// 展开次数超过 warn 但没有超过 limit 时给出一个警告。stable上的过程宏没有办法直接发出警告，
+// 这里借助`#[deprecated]`来实现，生成的是一个item，所以只在展开结果的开头允许插入item的时候插入
+fn size_warning(span: proc_macro2::Span, total: usize, warn: usize) -> proc_macro2::TokenStream {
+    let note = format!(
+        "seq! expands its body {} times, which is more than the warning threshold of {}; \
+         raise it with `warn = {}` before the variables if this is intended",
+        total, warn, total,
+    );
+    let ident = syn::Ident::new("SeqExpandsManyTimes", span);
+    quote::quote_spanned! {span=>
+        const _: () = {
+            #[deprecated(note = #note)]
+            struct #ident;
+            let _ = #ident;
+        };
+    }
 }
 
 // 定义解析自己语法，首先需要定义自己的语法树节点
 struct SeqParser {
-    variable_ident: syn::Ident,
-    start: isize,
-    end: isize,
+    // `N in 0..4, M in 0..4`这种写法可以定义多个循环变量，按照笛卡尔积展开，写在前面的变量在外层
+    variables: Vec<SeqVariable>,
     body: proc_macro2::TokenStream,
+    // 循环体展开次数超过这个值时给出警告
+    warn: usize,
+    // 需要给出警告时，记录警告的位置和循环体展开的次数
+    warning: Option<(proc_macro2::Span, usize)>,
+}
+
+// 默认的展开次数上限和警告阈值，可以用`seq!(limit = 100000, N in 0..100000 { ... })`修改。
+// 循环体展开次数超过上限直接报错，避免生成一个巨大的TokenStream把rustc卡住
+const DEFAULT_LIMIT: usize = 65536;
+const DEFAULT_WARN: usize = 16384;
+
+// 一个循环变量（或者用模式绑定的一组变量）以及它的取值，对应`N in 0..512`、`T in [u8, u16]`这样的片段
+struct SeqVariable {
+    // 模式中的所有标识符，例如`(I, (T, S))`中的`I`、`T`、`S`
+    idents: Vec<syn::Ident>,
+    // `in`后面的序列所在的位置，用于报告展开次数过多之类的错误
+    span: proc_macro2::Span,
+    // 按展开顺序排列好的每一次迭代中 idents 对应的取值，step_by、rev 等修饰在解析时就已经应用好了
+    rows: Vec<Vec<Binding>>,
+}
+
+// `in`后面的序列，shape 描述了每一项的结构，例如`.enumerate()`以后每一项都变成了`(序号, 原来的项)`
+struct Sequence {
+    shape: Shape,
+    rows: Vec<Vec<Binding>>,
+}
+
+// 还没有展开成列表的范围，用等差数列表示。step_by、rev可以直接在等差数列上计算，
+// 这样`(0..100000).step_by(1000)`按照最终的100个取值检查上限，也不会先生成一个巨大的列表
+struct Progression {
+    first: i128,
+    step: i128,
+    count: i128,
+    radix: Option<(u32, usize)>,
+    // 范围所在的位置，用于报告取值个数超过上限的错误
+    span: proc_macro2::Span,
+}
+
+// 解析过程中的序列，范围在用到enumerate、zip之前一直保持为等差数列
+enum PendingSequence {
+    Range(Progression),
+    Items(Sequence),
+}
+
+#[derive(PartialEq)]
+enum Shape {
+    Single,
+    Pair(Box<Shape>, Box<Shape>),
+}
+
+impl Shape {
+    // 用于报错时提示应该怎么写模式，例如`(_, (_, _))`
+    fn describe(&self) -> String {
+        match self {
+            Shape::Single => "_".to_string(),
+            Shape::Pair(a, b) => format!("({}, {})", a.describe(), b.describe()),
+        }
+    }
+}
+
+// `N in 0..64 step 8` 中的`step`、`keep_radix`以及`limit = 65536`中的`limit`、`warn`并不是Rust的关键字，需要自定义
+mod kw {
+    syn::custom_keyword!(step);
+    syn::custom_keyword!(limit);
+    syn::custom_keyword!(warn);
+    syn::custom_keyword!(keep_radix);
 }
 
 // SeqParser 实现 syn::parse::Parse 的trait,从而提供将TokenStream 解析成 ast 的能力
 impl syn::parse::Parse for SeqParser {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        // 我们要解析形如 `N in 0..512 {.......}` 这样的代码片段
+        // 我们要解析形如 `N in 0..512 {.......}` 或者 `N in 0..4, M in 0..4 {.......}` 这样的代码片段
+        // 最前面可以写上`limit = 100000, warn = 50000,`这样的选项，修改展开次数的上限和警告阈值。
+        // 选项必须写在循环变量之前，这样解析范围的时候就能知道上限，不会先生成一个巨大的列表
+        let mut limit = DEFAULT_LIMIT;
+        let mut warn = DEFAULT_WARN;
+        while (input.peek(kw::limit) || input.peek(kw::warn)) && input.peek2(syn::Token![=]) {
+            let is_limit = input.peek(kw::limit);
+            let _: proc_macro2::Ident = input.call(syn::ext::IdentExt::parse_any)?;
+            input.parse::<syn::Token![=]>()?;
+            let value = input.parse::<syn::LitInt>()?.base10_parse::<usize>()?;
+            if is_limit {
+                limit = value;
+            } else {
+                warn = value;
+            }
+            input.parse::<syn::Token![,]>()?;
+        }
+
+        let mut variables:Vec<SeqVariable> = Vec::new();
+        loop {
+            let variable = SeqVariable::parse(input, limit)?;
+            for ident in &variable.idents {
+                if variables.iter().flat_map(|v| &v.idents).any(|bound| bound == ident) {
+                    return Err(syn::Error::new_spanned(
+                        ident,
+                        format!("variable `{}` is bound more than once", ident),
+                    ));
+                }
+            }
+            variables.push(variable);
+            if !input.peek(syn::Token![,]) {
+                break;
+            }
+            input.parse::<syn::Token![,]>()?;
+        }
+
+        // 这里展示了braced!宏的用法，用于把一个代码块整体读取出来，如果读取成功就将代码块
+        // 内部数据作为一个`ParseBuffer`类型的数据返回，同时把读取游标移动到整个代码块的后面
+        let body_buf;
+        syn::braced!(body_buf in input);
+        let body:proc_macro2::TokenStream = body_buf.parse()?;
+
+        // 多个变量按照笛卡尔积展开，展开的次数是各个变量取值个数的乘积
+        let mut total: usize = 1;
+        let mut warning = None;
+        for variable in &variables {
+            let next = (total as u128) * (variable.rows.len() as u128);
+            if next > limit as u128 {
+                return Err(syn::Error::new(
+                    variable.span,
+                    format!(
+                        "seq! would expand its body {} times, which exceeds the limit of {}; \
+                         raise it with `limit = {}` before the variables if this is intended",
+                        next, limit, next,
+                    ),
+                ));
+            }
+            total = next as usize;
+            if total > warn && warning.is_none() {
+                warning = Some(variable.span);
+            }
+        }
+
+        Ok(SeqParser{
+            variables,
+            body,
+            warn,
+            warning: warning.map(|span| (span, total)),
+        })
+    }
+}
+
+impl SeqVariable {
+    // limit 是展开次数的上限，范围中的取值个数超过上限时直接报错
+    fn parse(input: syn::parse::ParseStream, limit: usize) -> syn::Result<Self> {
         // 假定`ParseStream`当前游标对应的是一个可以解析为`Ident`类型的Token，
         // 如果真的是`Ident`类型节点，则返回Ok并将当前读取游标向后移动一个Token
         // 如果不是`Ident`类型，则返回Err,说明语法错误，直接返回
-        let variable_ident:syn::Ident = input.parse()?;
+        // 也可以写成`(I, N)`这样的模式，和`.enumerate()`、`.zip(...)`配合同时绑定多个变量
+        let pattern_span = input
+            .cursor()
+            .token_tree()
+            .map_or_else(|| input.span(), |(tt, _)| tt.span());
+        let mut idents = Vec::new();
+        let shape = Self::parse_pattern(input, &mut idents)?;
 
         // 假定`ParseStream`当前游标对应的是一个写作`in`的自定义的Token
         input.parse::<syn::Token![in]>()?;
 
-        // 假定`ParseStream`当前游标对应的是一个可以解析为整形数字面量的Token，
-        let start_lit:syn::LitInt = input.parse()?;
+        let span = input.span();
+        let mut sequence = Self::parse_sequence(input, limit)?;
+
+        // 范围的边界用十六进制、八进制或二进制书写时，默认仍然按照十进制输出，
+        // 后面写上`keep_radix`才按照边界书写时的进制输出，例如`0x00..0x10 keep_radix`展开为`0x00`...`0x0f`
+        if input.peek(kw::keep_radix) {
+            input.parse::<kw::keep_radix>()?;
+        } else {
+            for binding in sequence.rows.iter_mut().flatten() {
+                if let Binding::Int { radix, .. } = binding {
+                    *radix = None;
+                }
+            }
+        }
+
+        if shape != sequence.shape {
+            return Err(syn::Error::new(
+                pattern_span,
+                format!(
+                    "the pattern does not match the items of the sequence, expected a pattern like `{}`",
+                    sequence.shape.describe(),
+                ),
+            ));
+        }
+
+        if sequence.rows.is_empty() {
+            return Err(syn::Error::new(span, "the sequence is empty, so the body would never be expanded"));
+        }
+
+        Ok(SeqVariable{
+            idents,
+            span,
+            rows: sequence.rows,
+        })
+    }
+
+    // 解析`N`或者`(I, (T, S))`这样的模式，把其中的标识符按顺序放进 idents
+    fn parse_pattern(input: syn::parse::ParseStream, idents: &mut Vec<syn::Ident>) -> syn::Result<Shape> {
+        if !input.peek(syn::token::Paren) {
+            idents.push(input.parse()?);
+            return Ok(Shape::Single);
+        }
+        let pattern_buf;
+        syn::parenthesized!(pattern_buf in input);
+        let first = Self::parse_pattern(&pattern_buf, idents)?;
+        pattern_buf.parse::<syn::Token![,]>()?;
+        let second = Self::parse_pattern(&pattern_buf, idents)?;
+        if !pattern_buf.is_empty() {
+            return Err(pattern_buf.error("expected a pattern with two elements"));
+        }
+        Ok(Shape::Pair(Box::new(first), Box::new(second)))
+    }
+
+    // 解析`in`后面的序列：范围、`[u8, u16]`这样的列表，以及后面跟着的`.rev()`等方法调用
+    fn parse_sequence(input: syn::parse::ParseStream, limit: usize) -> syn::Result<Sequence> {
+        // 范围可以用括号括起来，后面跟上和迭代器一样的方法调用，例如`(0..64).step_by(8)`、`(0..8).rev()`
+        let mut bare_range = false;
+        let mut pending = if input.peek(syn::token::Bracket) {
+            let list_buf;
+            syn::bracketed!(list_buf in input);
+            PendingSequence::Items(Self::parse_list(&list_buf)?)
+        } else if Self::is_parenthesized_range(input) {
+            let range_buf;
+            syn::parenthesized!(range_buf in input);
+            let progression = Self::parse_range(&range_buf)?;
+            if !range_buf.is_empty() {
+                return Err(range_buf.error("unexpected tokens after range"));
+            }
+            PendingSequence::Range(progression)
+        } else {
+            // 不带括号的范围后面不能直接跟方法调用
+            bare_range = true;
+            PendingSequence::Range(Self::parse_range(input)?)
+        };
+
+        while !bare_range && input.peek(syn::Token![.]) {
+            input.parse::<syn::Token![.]>()?;
+            let method:syn::Ident = input.parse()?;
+            let args_buf;
+            syn::parenthesized!(args_buf in input);
+            if method == "step_by" {
+                let step = Self::parse_step(&args_buf.parse()?)?;
+                pending.step_by(step);
+            } else if method == "rev" {
+                pending.rev();
+            } else if method == "enumerate" {
+                // 每一项前面加上从0开始的序号
+                let mut sequence = pending.into_sequence(limit)?;
+                sequence.rows = sequence
+                    .rows
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, row)| {
+                        let mut ret = vec![Binding::Int { n: idx as isize, radix: None }];
+                        ret.extend(row);
+                        ret
+                    })
+                    .collect();
+                sequence.shape = Shape::Pair(Box::new(Shape::Single), Box::new(sequence.shape));
+                pending = PendingSequence::Items(sequence);
+            } else if method == "zip" {
+                // 和另一个序列一一配对，较长的序列多出来的部分被丢弃
+                let mut sequence = pending.into_sequence(limit)?;
+                let other = Self::parse_sequence(&args_buf, limit)?;
+                sequence.rows = sequence
+                    .rows
+                    .into_iter()
+                    .zip(other.rows)
+                    .map(|(mut row, other_row)| {
+                        row.extend(other_row);
+                        row
+                    })
+                    .collect();
+                sequence.shape = Shape::Pair(Box::new(sequence.shape), Box::new(other.shape));
+                pending = PendingSequence::Items(sequence);
+            } else {
+                return Err(syn::Error::new_spanned(
+                    method,
+                    "expected `step_by(...)`, `rev()`, `enumerate()` or `zip(...)`",
+                ));
+            }
+            if !args_buf.is_empty() {
+                return Err(args_buf.error("unexpected arguments"));
+            }
+        }
+
+        // 也可以写成`N in 0..64 step 8`这种更简短的形式
+        if input.peek(kw::step) {
+            input.parse::<kw::step>()?;
+            let step = Self::parse_step(&input.parse()?)?;
+            pending.step_by(step);
+        }
+        pending.into_sequence(limit)
+    }
+
+    // 解析`[u8, u16, u32]`这样的列表，每一项可以是类型、标识符或者表达式
+    fn parse_list(input: syn::parse::ParseStream) -> syn::Result<Sequence> {
+        let mut rows = Vec::new();
+        while !input.is_empty() {
+            // 先尝试按类型解析，这样`HashMap<K, V>`中的逗号不会被当成分隔符
+            let fork = input.fork();
+            let tokens = if fork.parse::<syn::Type>().is_ok() && (fork.is_empty() || fork.peek(syn::Token![,])) {
+                quote::ToTokens::into_token_stream(input.parse::<syn::Type>()?)
+            } else {
+                quote::ToTokens::into_token_stream(input.parse::<syn::Expr>()?)
+            };
+            // 不带后缀的整数字面量（例如`4`、`-1`、`0x10`）当作整数处理，这样也能参与`~{T * 2}`这样的计算，
+            // 其它的项（包括`1u8`、`1 + 1`）原样替换，不能丢掉后缀或者改写表达式
+            let parser = |input: syn::parse::ParseStream| {
+                let negative = input.parse::<Option<syn::Token![-]>>()?.is_some();
+                let lit:syn::LitInt = input.parse()?;
+                if !lit.suffix().is_empty() {
+                    return Err(syn::Error::new_spanned(&lit, "suffixed literal"));
+                }
+                let mut radix = None;
+                let n = syn::parse::Parser::parse2(
+                    |input: syn::parse::ParseStream| Self::parse_bound(input, &mut radix, &|_| None),
+                    quote::quote!(#lit),
+                )?;
+                Ok(Binding::Int { n: if negative { -n } else { n }, radix })
+            };
+            let item = syn::parse::Parser::parse2(parser, tokens.clone()).unwrap_or(Binding::Tokens(tokens));
+            rows.push(vec![item]);
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<syn::Token![,]>()?;
+        }
+        Ok(Sequence { shape: Shape::Single, rows })
+    }
+
+    // 判断括号中是否是一个完整的范围，例如`(0..8).rev()`，用来和`(1 << 2)..8`这种
+    // 只是给边界加了括号的写法区分开
+    fn is_parenthesized_range(input: syn::parse::ParseStream) -> bool {
+        let fork = input.fork();
+        let is_range = || -> syn::Result<bool> {
+            let range_buf;
+            syn::parenthesized!(range_buf in fork);
+            Self::parse_bound(&range_buf, &mut None, &|_| None)?;
+            Ok(range_buf.peek(syn::Token![..]))
+        };
+        is_range().unwrap_or(false)
+    }
+
+    // 解析`0..512`或者`0..=512`，返回范围中的所有取值
+    fn parse_range(input: syn::parse::ParseStream) -> syn::Result<Progression> {
+        let span = input.span();
+        let mut radix = None;
+        // 边界可以是负数，也可以是由整数字面量组成的简单算术表达式，例如`-4`、`1 << 4`
+        let start = Self::parse_bound(input, &mut radix, &|_| None)?;
 
         // 假定`ParseStream`当前游标对应的是一个写作`..`的自定义的Token
         input.parse::<syn::Token![..]>()?;
@@ -56,176 +435,788 @@ impl syn::parse::Parse for SeqParser {
             inc = true;
         }
 
+        let end = Self::parse_bound(input, &mut radix, &|_| None)?;
+        // `5..0`这样的范围在Rust中是空的，这里直接报错，而不是什么都不生成
+        if start > end {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "range start {} is greater than its end {}; write the range in ascending order and add `.rev()` to count down",
+                    start, end,
+                ),
+            ));
+        }
+
+        Ok(Progression {
+            first: start as i128,
+            step: 1,
+            count: (end as i128) - (start as i128) + (inc as i128),
+            radix,
+            span,
+        })
+    }
+
+    // 解析由整数字面量组成的算术表达式并求值，variables 用来查找表达式中用到的循环变量，
+    // 例如循环体中的`~{N * 4}`
+    fn parse_bound(
+        input: syn::parse::ParseStream,
+        radix: &mut Option<(u32, usize)>,
+        variables: &dyn Fn(&syn::Ident) -> Option<Binding>,
+    ) -> syn::Result<isize> {
+        Self::parse_binary_expr(input, 0, radix, variables)
+    }
+
+    // 用优先级爬升的方式解析二元运算，min_prec 是当前允许的最低优先级，
+    // 运算符的优先级和Rust保持一致
+    fn parse_binary_expr(
+        input: syn::parse::ParseStream,
+        min_prec: u8,
+        radix: &mut Option<(u32, usize)>,
+        variables: &dyn Fn(&syn::Ident) -> Option<Binding>,
+    ) -> syn::Result<isize> {
+        let mut lhs = Self::parse_unary_expr(input, radix, variables)?;
+        loop {
+            let (op, prec) = if input.peek(syn::Token![<<]) {
+                ("<<", 3)
+            } else if input.peek(syn::Token![>>]) {
+                (">>", 3)
+            } else if input.peek(syn::Token![*]) {
+                ("*", 5)
+            } else if input.peek(syn::Token![/]) {
+                ("/", 5)
+            } else if input.peek(syn::Token![%]) {
+                ("%", 5)
+            } else if input.peek(syn::Token![+]) {
+                ("+", 4)
+            } else if input.peek(syn::Token![-]) {
+                ("-", 4)
+            } else if input.peek(syn::Token![&]) {
+                ("&", 2)
+            } else if input.peek(syn::Token![^]) {
+                ("^", 1)
+            } else if input.peek(syn::Token![|]) {
+                ("|", 0)
+            } else {
+                break;
+            };
+            if prec < min_prec {
+                break;
+            }
+            // 记下运算符的位置，溢出或者除以零的时候报错指向这里
+            let op_span = input.span();
+            for _ in 0..op.len() {
+                input.parse::<proc_macro2::Punct>()?;
+            }
+            let rhs = Self::parse_binary_expr(input, prec + 1, radix, variables)?;
+            let value = match op {
+                "<<" => u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shl(rhs)),
+                ">>" => u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shr(rhs)),
+                "*" => lhs.checked_mul(rhs),
+                "/" => lhs.checked_div(rhs),
+                "%" => lhs.checked_rem(rhs),
+                "+" => lhs.checked_add(rhs),
+                "-" => lhs.checked_sub(rhs),
+                "&" => Some(lhs & rhs),
+                "^" => Some(lhs ^ rhs),
+                _ => Some(lhs | rhs),
+            };
+            lhs = value.ok_or_else(|| {
+                syn::Error::new(op_span, format!("cannot evaluate `{} {} {}`", lhs, op, rhs))
+            })?;
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary_expr(
+        input: syn::parse::ParseStream,
+        radix: &mut Option<(u32, usize)>,
+        variables: &dyn Fn(&syn::Ident) -> Option<Binding>,
+    ) -> syn::Result<isize> {
+        if input.peek(syn::Token![-]) {
+            let minus = input.parse::<syn::Token![-]>()?;
+            let value = Self::parse_unary_expr(input, radix, variables)?;
+            return value
+                .checked_neg()
+                .ok_or_else(|| syn::Error::new(minus.span, format!("cannot evaluate `-{}`", value)));
+        }
+        if input.peek(syn::token::Paren) {
+            let expr_buf;
+            syn::parenthesized!(expr_buf in input);
+            let value = Self::parse_bound(&expr_buf, radix, variables)?;
+            if !expr_buf.is_empty() {
+                return Err(expr_buf.error("unexpected token in range bound"));
+            }
+            return Ok(value);
+        }
+        // macro_rules!传进来的`$e:expr`带有一层不可见的分组，按照括号处理，
+        // 这样`~{$e * 2}`中的`$e`是`1 + 1`时结果是4而不是3
+        if let Some((inner, _, _)) = input.cursor().group(proc_macro2::Delimiter::None) {
+            let parser = |expr_buf: syn::parse::ParseStream| Self::parse_bound(expr_buf, radix, variables);
+            let value = syn::parse::Parser::parse2(parser, inner.token_stream())?;
+            input.step(|cursor| {
+                let (_, _, rest) = cursor.group(proc_macro2::Delimiter::None).unwrap();
+                Ok(((), rest))
+            })?;
+            return Ok(value);
+        }
+
+        // 循环变量按照当前的取值参与计算
+        if input.peek(syn::Ident) {
+            let ident:syn::Ident = input.parse()?;
+            return match variables(&ident) {
+                Some(Binding::Int { n, .. }) => Ok(n),
+                Some(Binding::Tokens(_)) => {
+                    Err(syn::Error::new_spanned(&ident, format!("loop variable `{}` is not an integer", ident)))
+                }
+                None => Err(syn::Error::new_spanned(&ident, format!("unknown loop variable `{}`", ident))),
+            };
+        }
+
         // 假定`ParseStream`当前游标对应的是一个可以解析为整形数字面量的Token，
-        let end_lit:syn::LitInt = input.parse()?;
+        let lit:syn::LitInt = input.parse()?;
+        // 记录第一个非十进制字面量的进制和位数，展开时按照这个格式输出
+        if radix.is_none() {
+            let repr = lit.to_string();
+            let repr = repr.strip_suffix(lit.suffix()).unwrap_or(&repr);
+            let found = [("0x", 16), ("0o", 8), ("0b", 2)]
+                .into_iter()
+                .find_map(|(prefix, base)| repr.strip_prefix(prefix).map(|digits| (base, digits)));
+            if let Some((base, digits)) = found {
+                *radix = Some((base, digits.chars().filter(|c| *c != '_').count()));
+            }
+        }
+        lit.base10_parse()
+    }
 
-        // 这里展示了braced!宏的用法，用于把一个代码块整体读取出来，如果读取成功就将代码块
-        // 内部数据作为一个`ParseBuffer`类型的数据返回，同时把读取游标移动到整个代码块的后面
-        let body_buf;
-        syn::braced!(body_buf in input);
-        let body:proc_macro2::TokenStream = body_buf.parse()?;
-        
-        let mut t = SeqParser{
-            variable_ident,
-            start:start_lit.base10_parse()?,
-            end:end_lit.base10_parse()?,
-            body
-        };
+    // 解析`step_by(8)`、`step 8`中的步长
+    fn parse_step(step_lit: &syn::LitInt) -> syn::Result<usize> {
+        let step:usize = step_lit.base10_parse()?;
+        if step == 0 {
+            return Err(syn::Error::new_spanned(step_lit, "step must be greater than zero"));
+        }
+        Ok(step)
+    }
+}
 
-        if inc {
-            t.end += 1;
+impl PendingSequence {
+    // 和`Iterator::step_by`一样，保留第一个值以及之后每隔 step 个的值
+    fn step_by(&mut self, step: usize) {
+        match self {
+            PendingSequence::Range(progression) => {
+                progression.step *= step as i128;
+                progression.count = (progression.count + step as i128 - 1) / step as i128;
+            }
+            PendingSequence::Items(sequence) => {
+                sequence.rows = std::mem::take(&mut sequence.rows).into_iter().step_by(step).collect();
+            }
         }
+    }
 
-        Ok(t)
+    fn rev(&mut self) {
+        match self {
+            PendingSequence::Range(progression) => {
+                if progression.count > 0 {
+                    progression.first += (progression.count - 1) * progression.step;
+                }
+                progression.step = -progression.step;
+            }
+            PendingSequence::Items(sequence) => sequence.rows.reverse(),
+        }
     }
+
+    // 把范围展开成每一项的取值，展开之前先检查取值的个数，超过上限时直接报错，不去生成这个列表
+    fn into_sequence(self, limit: usize) -> syn::Result<Sequence> {
+        let progression = match self {
+            PendingSequence::Range(progression) => progression,
+            PendingSequence::Items(sequence) => return Ok(sequence),
+        };
+        if progression.count > limit as i128 {
+            return Err(syn::Error::new(
+                progression.span,
+                format!(
+                    "range produces {} values, which exceeds the limit of {}; \
+                     raise it with `limit = {}` before the variables if this is intended",
+                    progression.count, limit, progression.count,
+                ),
+            ));
+        }
+        let Progression { first, step, count, radix, .. } = progression;
+        Ok(Sequence {
+            shape: Shape::Single,
+            rows: (0..count)
+                .map(|i| vec![Binding::Int { n: (first + i * step) as isize, radix }])
+                .collect(),
+        })
+    }
+}
+
+// 循环变量在某一次迭代中的取值
+#[derive(Clone)]
+enum Binding {
+    // 整数。范围的边界用十六进制、八进制或二进制字面量书写并且写了`keep_radix`时，radix 记录下进制和数字的位数，
+    // 展开时按照同样的进制输出，例如`0x00..0x10 keep_radix`展开为`0x00`、`0x01`...`0x0f`
+    Int { n: isize, radix: Option<(u32, usize)> },
+    // 列表中的一项，可以是类型、标识符、表达式等任意代码，例如`T in [u8, u16]`中的`u8`
+    Tokens(proc_macro2::TokenStream),
 }
 
+impl Binding {
+    // 匹配`N as u8`，返回带类型后缀的字面量，取值超出类型范围时报错
+    fn typed_literal<'a>(
+        &self,
+        span: proc_macro2::Span,
+        rest: syn::buffer::Cursor<'a>,
+    ) -> syn::Result<Option<(proc_macro2::Literal, syn::buffer::Cursor<'a>)>> {
+        let Binding::Int { n, .. } = *self else {
+            return Ok(None);
+        };
+        // 类型可能是macro_rules!传进来的`$t:ty`，ident()会自动进入这种不可见的分组
+        let Some((as_token, rest)) = rest.ident() else {
+            return Ok(None);
+        };
+        let Some((ty, rest)) = rest.ident() else {
+            return Ok(None);
+        };
+        if as_token != "as" {
+            return Ok(None);
+        }
+        let value = n as i128;
+        let range = match ty.to_string().as_str() {
+            "i8" => i8::MIN as i128..=i8::MAX as i128,
+            "i16" => i16::MIN as i128..=i16::MAX as i128,
+            "i32" => i32::MIN as i128..=i32::MAX as i128,
+            "i64" => i64::MIN as i128..=i64::MAX as i128,
+            "i128" => i128::MIN..=i128::MAX,
+            "isize" => isize::MIN as i128..=isize::MAX as i128,
+            "u8" => 0..=u8::MAX as i128,
+            "u16" => 0..=u16::MAX as i128,
+            "u32" => 0..=u32::MAX as i128,
+            "u64" => 0..=u64::MAX as i128,
+            "u128" | "usize" => 0..=i128::MAX,
+            // 浮点数字面量只有十进制的写法，例如`1f32`
+            "f32" | "f64" => {
+                let mut literal:proc_macro2::Literal = format!("{}{}", n, ty).parse().unwrap();
+                literal.set_span(span);
+                return Ok(Some((literal, rest)));
+            }
+            // 其它类型保持原样，交给编译器做普通的类型转换
+            _ => return Ok(None),
+        };
+        if !range.contains(&value) {
+            return Err(syn::Error::new(
+                span,
+                format!("value `{}` does not fit in `{}`", n, ty),
+            ));
+        }
+        let mut literal:proc_macro2::Literal = format!("{}{}", self.format_value(true), ty).parse().unwrap();
+        literal.set_span(span);
+        Ok(Some((literal, rest)))
+    }
+
+    // 按照范围边界书写时的进制格式化一个取值，with_prefix 表示是否带上`0x`这类前缀，
+    // 拼接到标识符中的时候不带前缀，例如`Reg~N`展开为`Reg0a`。列表中的项直接转换为字符串
+    fn format_value(&self, with_prefix: bool) -> String {
+        let (n, radix) = match self {
+            Binding::Int { n, radix } => (*n, *radix),
+            Binding::Tokens(tokens) => return tokens.to_string(),
+        };
+        let Some((base, width)) = radix else {
+            return n.to_string();
+        };
+        let digits = match base {
+            16 => format!("{:0width$x}", n.unsigned_abs()),
+            8 => format!("{:0width$o}", n.unsigned_abs()),
+            _ => format!("{:0width$b}", n.unsigned_abs()),
+        };
+        let prefix = match (with_prefix, base) {
+            (false, _) => "",
+            (true, 16) => "0x",
+            (true, 8) => "0o",
+            (true, _) => "0b",
+        };
+        let sign = if n < 0 { "-" } else { "" };
+        format!("{sign}{prefix}{digits}")
+    }
+
+    // 生成替换`N`的代码，整数输出为字面量，列表中的项由多个Token组成时用不可见的分组包起来，
+    // 和macro_rules!中的`$t:ty`一样，避免和周围的代码组合出错误的优先级
+    fn to_token_stream(&self) -> proc_macro2::TokenStream {
+        match self {
+            Binding::Int { radix: Some(_), .. } => {
+                let literal:proc_macro2::Literal = self.format_value(true).parse().unwrap();
+                quote::quote!(#literal)
+            }
+            Binding::Int { n, radix: None } => {
+                let literal = proc_macro2::Literal::i64_unsuffixed(*n as i64);
+                quote::quote!(#literal)
+            }
+            Binding::Tokens(tokens) if tokens.clone().into_iter().count() == 1 => tokens.clone(),
+            Binding::Tokens(tokens) => {
+                let group = proc_macro2::Group::new(proc_macro2::Delimiter::None, tokens.clone());
+                quote::quote!(#group)
+            }
+        }
+    }
+}
 
 impl SeqParser {
-    fn expand(&self, ts: &proc_macro2::TokenStream, n: isize) -> proc_macro2::TokenStream {
-        let buf = ts.clone().into_iter().collect::<Vec<_>>();
-        let mut ret = proc_macro2::TokenStream::new();
-        
-        // 这里为了简单，使用了for循环，实际我想表达的意思是，
-        // 这个idx你可以随心所欲的控制，跳着访问，回溯已经访问过的节点等等，都可以
-        let mut idx = 0;
-        while idx < buf.len() {
-            let tree_node = &buf[idx];
-            match tree_node {
-                proc_macro2::TokenTree::Group(g) => {
-                    // 如果是括号包含的内容，我们就要递归处理内部的TokenStream
-                    let new_stream = self.expand(&g.stream(), n);
-                    // 这里需要注意，上一行中g.stream()返回的是Group内部的TokenStream，
-                    // 也就是说不包含括号本身，所以要在下面重新套上一层括号，而且括号的
-                    // 种类要与原来保持一致。 
-                    let mut wrap_in_group = proc_macro2::Group::new(g.delimiter(), new_stream);
-                    wrap_in_group.set_span(g.span());
-                    ret.extend(quote::quote!(#wrap_in_group));
+    // 需要给出警告并且展开结果的开头可以插入item时，在展开结果前面加上警告
+    fn with_size_warning(&self, expanded: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match self.warning {
+            Some((span, total)) if starts_with_item_or_statement(&expanded) => {
+                let mut ret = size_warning(span, total, self.warn);
+                ret.extend(expanded);
+                ret
+            }
+            _ => expanded,
+        }
+    }
+
+    // 在已经绑定的 positions 基础上，按照笛卡尔积列出 which 中的变量的每一种组合。
+    // positions 和 self.variables 一一对应，记录的是每个变量当前取到了第几个值，
+    // None 表示这个变量没有绑定，例如被嵌套的seq!重新定义的变量，不做替换
+    fn iterations(&self, which: &[usize], positions: &[Option<usize>]) -> Vec<Vec<Option<usize>>> {
+        let mut ret = vec![positions.to_vec()];
+        for &var_idx in which {
+            ret = ret
+                .into_iter()
+                .flat_map(|prefix| {
+                    (0..self.variables[var_idx].rows.len()).map(move |pos| {
+                        let mut positions = prefix.clone();
+                        positions[var_idx] = Some(pos);
+                        positions
+                    })
+                })
+                .collect();
+        }
+        ret
+    }
+
+    // 查找标识符对应的循环变量在本次迭代中的取值
+    fn lookup(&self, name: &str, positions: &[Option<usize>]) -> Option<Binding> {
+        self.variables.iter().zip(positions).find_map(|(variable, pos)| {
+            let row = &variable.rows[(*pos)?];
+            variable.idents.iter().position(|ident| ident == name).map(|k| row[k].clone())
+        })
+    }
+
+    // 找出一段代码中用到的循环变量在 self.variables 中的下标，字符串中的`~N`也算
+    fn referenced_variables(&self, cursor: syn::buffer::Cursor) -> Vec<usize> {
+        let mut ret = Vec::new();
+        let mut visit = |name: &str| {
+            for (var_idx, variable) in self.variables.iter().enumerate() {
+                if variable.idents.iter().any(|ident| ident == name) && !ret.contains(&var_idx) {
+                    ret.push(var_idx);
                 }
-                proc_macro2::TokenTree::Ident(prefix) => {
-                    if idx + 2 < buf.len() { // 我们需要向后预读两个TokenTree元素
-                        if let proc_macro2::TokenTree::Punct(p) = &buf[idx + 1] {
-                            // 井号是一个比较少见的符号，
-                            // 我们尽量早一些判断井号是否存在，这样就可以尽快否定掉不匹配的模式
-                            if p.as_char() == '~' { 
-                                if let proc_macro2::TokenTree::Ident(i) = &buf[idx + 2] {
-                                    if i == &self.variable_ident
-                                        && prefix.span().end() == p.span().start() // 校验是否连续，无空格
-                                        && p.span().end() == i.span().start()
-                                    {
-                                        
-                                        let new_ident_litral = format!("{}{}", prefix.to_string(), n);
-                                        let new_ident = proc_macro2::Ident::new(new_ident_litral.as_str(), prefix.span());
-                                        ret.extend(quote::quote!(#new_ident));
-                                        idx += 3; // 我们消耗了3个Token，所以这里要加3
-                                        continue;
-                                    }
-                                }
-                            }
+            }
+        };
+        fn walk(mut cursor: syn::buffer::Cursor, visit: &mut dyn FnMut(&str)) {
+            while !cursor.eof() {
+                if let Some((inner, _, _, next)) = cursor.any_group() {
+                    walk(inner, visit);
+                    cursor = next;
+                } else if let Some((ident, next)) = cursor.ident() {
+                    visit(&ident.to_string());
+                    cursor = next;
+                } else if let Some((literal, next)) = cursor.literal() {
+                    if let syn::Lit::Str(lit_str) = syn::Lit::new(literal) {
+                        for part in lit_str.value().split('~').skip(1) {
+                            let name_len = part
+                                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                                .unwrap_or(part.len());
+                            visit(&part[..name_len]);
                         }
                     }
-                    // 虽然这一关要支持新的模式，可以为了通过前面的关卡，老逻辑也得兼容。
-                    // 写Parser的一个通用技巧：当有多个可能冲突的规则时，优先尝试最长的
-                    // 规则，因为这个规则只需要看一个Token，而上面的规则需要看3个Token，
-                    // 所以这个规则要写在上一个规则的下面，否则就会导致短规则抢占，长规则无法命中。
-                    if prefix == &self.variable_ident {
-                        let new_ident = proc_macro2::Literal::i64_unsuffixed(n as i64);
-                        ret.extend(quote::quote!(#new_ident));
-                        idx += 1;
-                        continue;
-                    }
-                    ret.extend(quote::quote!(#tree_node));
-                }
-                _ => {
-                    // 对于其它的元素（也就是Punct和Literal），原封不动透传
-                    ret.extend(quote::quote!(#tree_node));
+                    cursor = next;
+                } else if let Some((_, next)) = cursor.token_tree() {
+                    cursor = next;
                 }
             }
-            idx+=1;
         }
+        walk(cursor, &mut visit);
+        ret.sort();
         ret
     }
 
-    fn find_block_to_expand_and_do_expand(&self, c: syn::buffer::Cursor) -> (proc_macro2::TokenStream, bool) {
-        let mut found = false;
+    // 展开 cursor 指向的代码，positions 中已经绑定的变量替换为当前的取值。
+    // sections 为true时，遇到的`#(...)*`按照其中用到的变量重复展开，其余的代码原样保留
+    fn expand(&self, c: syn::buffer::Cursor, positions: &[Option<usize>], sections: bool) -> syn::Result<proc_macro2::TokenStream> {
         let mut ret = proc_macro2::TokenStream::new();
 
+        // syn包提供的Cursor机制，并不是拿到一个Cursor以后，不断向后移动更新这个Cursor，
+        // 而是每次都会返回给你一个全新的Cursor，新的Cursor指向新的位置，
+        // 老的Cursor指向的位置保持不变。所以这里可以随心所欲地向后看几个Token，
+        // 匹配失败时直接丢掉新的Cursor就行，不需要回溯
         let mut cursor = c;
         while !cursor.eof() {
-            // 注意punct()这个函数的返回值，它会返回一个新的`Cursor`类型的值，
-            // 这个新的Cursor指向了消耗掉当前标点符号以后，在TokenBuffer中的下一个位置
-            // syn包提供的Cursor机制，并不是拿到一个Cursor以后，不断向后移动更新这个Cursor，
-            // 而是每次都会返回给你一个全新的Cursor，新的Cursor指向新的位置，
-            // 老的Cursor指向的位置保持不变
-            if let Some((punct_prefix, cursor_1)) = cursor.punct(){
-                if punct_prefix.as_char() == '#' {
-                    if let Some((group_cur,_,cursor_2)) = cursor_1.group(proc_macro2::Delimiter::Parenthesis) {
-                        if let Some((punct_suffix, cursor_3)) = cursor_2.punct() {
-                            if punct_suffix.as_char() == '*' {
-                                // 走到这里，说明找到了匹配的模式，按照指定的次数开始展开
-                                for i in self.start..self.end {
-                                    // 因为之前expand是用TokenStream这一套写的，所以
-                                    // 这里还要把Cursor转换为TokenStream。毕竟是演示嘛，
-                                    // 希望在最少的代码里用到最多的特性，如果是自己写的话，
-                                    // 可以用Cursor的方式来写expand函数，这样这里就可以
-                                    // 直接把Cursor传进去了
-                                    let t = self.expand(&group_cur.token_stream(), i);
-                                    ret.extend(t);
-                                }
-                                // 下面这行很重要，千万别忘了，把老的cursor丢了，替换成
-                                // 新的，相当于把游标向前移动了
-                                cursor = cursor_3;
-                                found = true;
-                                continue
-                            }
+            if sections {
+                if let Some((section, separator, next)) = repetition_section(cursor) {
+                    // 走到这里，说明找到了匹配的模式，按照指定的次数开始展开。
+                    // 每一段只遍历其中用到的变量，这样不同的段可以有不同的范围，
+                    // 一个变量都没用到的段按照所有变量的组合重复
+                    let mut which = self.referenced_variables(section);
+                    if which.is_empty() {
+                        which = (0..self.variables.len()).collect();
+                    }
+                    for (idx, positions) in self.iterations(&which, positions).iter().enumerate() {
+                        if idx > 0 {
+                            ret.extend(quote::quote!(#separator));
                         }
+                        ret.extend(self.expand(section, positions, false)?);
                     }
+                    // 下面这行很重要，千万别忘了，把老的cursor丢了，替换成
+                    // 新的，相当于把游标向前移动了
+                    cursor = next;
+                    continue
                 }
             }
-            // 走到这里，说明`#(xxxxxxxxx)*`这个模式没有匹配到，那么就要按照普通代码的各个元素来处理了。
-
-            // cursor也有用起来不方便的地方，比如在处理group的时候，我们没法统一处理()\[]\{}，需要把他们分别处理
-            // 有一种暴力的做法，就是cursor提供了token_tree()方法，可以把当前游标指向的内容作为一个TokenTree返回，
-            // 我们再去断言TokenTree是Group、Indet、Literal、Punct中的哪一种，这就相当于回到了上一关介绍的方法，
-            // 回到了`proc_macro2`包提供的工具上去。
-            // 所以我们这里本着尽量采用不重复的方式来讲解的原则，继续使用`cursor`提供的各种工具来完成本关题目
-            if let Some((group_cur,_, next_cur)) = cursor.group(proc_macro2::Delimiter::Brace) {
-                let (t, f) = self.find_block_to_expand_and_do_expand(group_cur);
-                found = f;
-                ret.extend(quote::quote!({#t}));
-                cursor = next_cur;
-                continue
-            } else if let Some((group_cur,_, next_cur)) = cursor.group(proc_macro2::Delimiter::Bracket) {
-                let (t, f) = self.find_block_to_expand_and_do_expand(group_cur);
-                found = f;
-                ret.extend(quote::quote!([#t]));
-                cursor = next_cur;
+
+            // 嵌套的seq!中重新定义的同名变量属于内层，外层不能替换，
+            // 内层的`#(...)*`也留给内层自己展开
+            if let Some((nested, next)) = self.expand_nested_seq(cursor, positions)? {
+                ret.extend(nested);
+                cursor = next;
                 continue
-            } else if let Some((group_cur,_, next_cur)) = cursor.group(proc_macro2::Delimiter::Parenthesis) {
-                let (t, f) = self.find_block_to_expand_and_do_expand(group_cur);
-                found = f;
-                ret.extend(quote::quote!((#t)));
-                cursor = next_cur;
+            }
+
+            // 分组要最先处理。cursor的ident()、punct()等方法会自动进入macro_rules!传进来的
+            // `$x:expr`这种不可见的分组(Delimiter::None)，直接用它们会把分组丢掉，
+            // 导致`$x * 2`这样的代码运算优先级出错，所以这里用any_group()统一处理各种括号
+            if let Some((inner, delimiter, delim_span, next)) = cursor.any_group() {
+                // 如果是括号包含的内容，我们就要递归处理内部的代码，
+                // 然后重新套上一层括号，而且括号的种类和位置要与原来保持一致
+                let mut wrap_in_group = proc_macro2::Group::new(delimiter, self.expand(inner, positions, sections)?);
+                wrap_in_group.set_span(delim_span.join());
+                ret.extend(quote::quote!(#wrap_in_group));
+                cursor = next;
                 continue
-            } else if let Some((punct ,next_cur)) = cursor.punct() {
+            }
+
+            if let Some((punct, next)) = cursor.punct() {
+                // 单独的`~{N * 2}`在展开时求值，输出一个整数字面量，可以用在数组长度、模式等
+                // 不能做运行时计算的地方
+                if punct.as_char() == '~' {
+                    if let Some((inner, delim_span, after)) = next.group(proc_macro2::Delimiter::Brace) {
+                        let span = delim_span.join();
+                        if punct.span().end() == span.start() {
+                            if let Some(binding) = self.evaluate(inner, positions)? {
+                                ret.extend(binding.to_token_stream().into_iter().map(|mut t| {
+                                    t.set_span(span);
+                                    t
+                                }));
+                                cursor = after;
+                                continue
+                            }
+                        }
+                    }
+                }
                 ret.extend(quote::quote!(#punct));
-                cursor = next_cur;
+                cursor = next;
                 continue
-            } else if let Some((ident ,next_cur)) = cursor.ident() {
+            }
+
+            if let Some((ident, next)) = cursor.ident() {
+                // 先尝试`f~N`、`f~N~_suffix`、`N~th`这种用`~`连起来的拼接链
+                if let Some((pasted, after)) = self.paste_chain(&ident, next, positions)? {
+                    ret.extend(quote::quote!(#pasted));
+                    cursor = after;
+                    continue
+                }
+                // 写Parser的一个通用技巧：当有多个可能冲突的规则时，优先尝试最长的
+                // 规则，所以单独的变量要写在拼接链的下面，否则就会导致短规则抢占，长规则无法命中。
+                // `N as u8`这种写法直接输出带后缀的字面量`0u8`，避免整数类型推断不出来
+                if let Some(binding) = self.lookup(&ident.to_string(), positions) {
+                    if let Some((literal, after)) = binding.typed_literal(ident.span(), next)? {
+                        ret.extend(quote::quote!(#literal));
+                        cursor = after;
+                        continue
+                    }
+                    // 替换出来的token用变量本身的位置，这样报错会指向循环体里用到变量的地方
+                    ret.extend(binding.to_token_stream().into_iter().map(|mut t| {
+                        t.set_span(ident.span());
+                        t
+                    }));
+                    cursor = next;
+                    continue
+                }
                 ret.extend(quote::quote!(#ident));
-                cursor = next_cur;
+                cursor = next;
                 continue
-            } else if let Some((literal ,next_cur)) = cursor.literal() {
+            }
+
+            if let Some((literal, next)) = cursor.literal() {
+                // 字符串字面量中的`~N`替换为当前的取值，可以用来生成属性和文档中的名字
+                if let syn::Lit::Str(lit_str) = syn::Lit::new(literal.clone()) {
+                    if let Some(replaced) = self.replace_in_str(&lit_str.value(), positions) {
+                        let new_literal = syn::LitStr::new(&replaced, literal.span());
+                        ret.extend(quote::quote!(#new_literal));
+                        cursor = next;
+                        continue
+                    }
+                }
                 ret.extend(quote::quote!(#literal));
-                cursor = next_cur;
+                cursor = next;
                 continue
-            } else if let Some((lifetime ,next_cur)) = cursor.lifetime() {
-                // lifetime这种特殊的分类也是用cursor模式来处理的时候特有的，之前`proc_macro2::TokenTree`里面没有定义这个分类
+            }
+
+            // lifetime这种特殊的分类也是用cursor模式来处理的时候特有的，`'a`中的`a`不能被当作循环变量替换
+            if let Some((lifetime, next)) = cursor.lifetime() {
                 ret.extend(quote::quote!(#lifetime));
-                cursor = next_cur;
+                cursor = next;
                 continue
             }
+
+            // 其它的情况，例如一个空的不可见分组之后的位置，原封不动透传
+            let Some((tree_node, next)) = cursor.token_tree() else {
+                break;
+            };
+            ret.extend(quote::quote!(#tree_node));
+            cursor = next;
+        }
+        Ok(ret)
+    }
+
+    // 从cursor开始匹配`seq!(...)`形式的嵌套调用，只替换其中没有被内层重新定义的变量，
+    // 返回展开后的调用以及调用之后的位置
+    fn expand_nested_seq<'a>(
+        &self,
+        cursor: syn::buffer::Cursor<'a>,
+        positions: &[Option<usize>],
+    ) -> syn::Result<Option<(proc_macro2::TokenStream, syn::buffer::Cursor<'a>)>> {
+        let Some((name, bang, inner, delimiter, delim_span, next)) = nested_seq(cursor) else {
+            return Ok(None);
+        };
+        let inner_variables = nested_seq_variables(&inner.token_stream());
+        let positions = self
+            .variables
+            .iter()
+            .zip(positions)
+            .map(|(variable, pos)| if variable.idents.iter().any(|ident| inner_variables.contains(ident)) { None } else { *pos })
+            .collect::<Vec<_>>();
+        let mut wrap_in_group = proc_macro2::Group::new(delimiter, self.expand(inner, &positions, false)?);
+        wrap_in_group.set_span(delim_span.join());
+        Ok(Some((quote::quote!(#name #bang #wrap_in_group), next)))
+    }
+
+    // 在展开时计算`~{N * 4}`中的表达式。表达式中用到了当前没有绑定的变量时（例如属于嵌套的seq!的变量）
+    // 返回None，原样保留交给内层处理
+    fn evaluate(&self, inner: syn::buffer::Cursor, positions: &[Option<usize>]) -> syn::Result<Option<Binding>> {
+        fn idents(ts: proc_macro2::TokenStream, ret: &mut Vec<proc_macro2::Ident>) {
+            for t in ts {
+                match t {
+                    proc_macro2::TokenTree::Ident(ident) => ret.push(ident),
+                    proc_macro2::TokenTree::Group(g) => idents(g.stream(), ret),
+                    _ => {}
+                }
+            }
         }
-        (ret, found)
+        let stream = inner.token_stream();
+        let mut used = Vec::new();
+        idents(stream.clone(), &mut used);
+        let Some(bindings) = used
+            .iter()
+            .map(|ident| self.lookup(&ident.to_string(), positions))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Ok(None);
+        };
+        // 结果沿用第一个用`keep_radix`保留了进制的变量的进制，表达式中的`0x10`这样的字面量不影响输出的进制
+        let var_radix = bindings.iter().find_map(|binding| match binding {
+            Binding::Int { radix, .. } => *radix,
+            Binding::Tokens(_) => None,
+        });
+        let parser = |input: syn::parse::ParseStream| {
+            let mut radix = None;
+            let n = SeqVariable::parse_bound(input, &mut radix, &|ident| self.lookup(&ident.to_string(), positions))?;
+            Ok(Binding::Int { n, radix: var_radix })
+        };
+        syn::parse::Parser::parse2(parser, stream).map(Some)
     }
-}
\ No newline at end of file
+
+    // 从first开始匹配形如`ident~ident~ident...`的拼接链，链中每两个相邻的Token之间都不能有空格，
+    // 其中等于循环变量的部分替换为当前的取值，其余部分原样拼接。
+    // 匹配成功时返回拼接出的Token以及拼接链之后的位置，至少要有一个`~`才算匹配成功
+    fn paste_chain<'a>(
+        &self,
+        first: &proc_macro2::Ident,
+        rest: syn::buffer::Cursor<'a>,
+        positions: &[Option<usize>],
+    ) -> syn::Result<Option<(proc_macro2::TokenTree, syn::buffer::Cursor<'a>)>> {
+        // 链中一个循环变量都没有时不拼接，例如嵌套的seq!中属于内层的`f~N`
+        let mut has_variable = false;
+        let mut pasted = match self.lookup(&first.to_string(), positions) {
+            Some(binding) => {
+                has_variable = true;
+                binding.format_value(false)
+            }
+            None => first.to_string(),
+        };
+        let mut links = 0;
+        let mut prev_span = first.span();
+        let mut cursor = rest;
+        // `~`是一个比较少见的符号，
+        // 我们尽量早一些判断`~`是否存在，这样就可以尽快否定掉不匹配的模式
+        while let Some((p, after_p)) = cursor.punct() {
+            if p.as_char() != '~' || prev_span.end() != p.span().start() {
+                break;
+            }
+            // 链中的每一节可以是标识符，也可以是`{N * 4}`这样的表达式
+            let (binding, text, span, next) = if let Some((ident, next)) = after_p.ident() {
+                (self.lookup(&ident.to_string(), positions), ident.to_string(), ident.span(), next)
+            } else if let Some((inner, delim_span, next)) = after_p.group(proc_macro2::Delimiter::Brace) {
+                match self.evaluate(inner, positions)? {
+                    Some(binding) => (Some(binding), String::new(), delim_span.join(), next),
+                    None => break,
+                }
+            } else {
+                break;
+            };
+            if p.span().end() != span.start() { // 校验是否连续，无空格
+                break;
+            }
+            match binding {
+                Some(binding) => {
+                    has_variable = true;
+                    pasted.push_str(&binding.format_value(false));
+                }
+                None => pasted.push_str(&text),
+            }
+            links += 1;
+            prev_span = span;
+            cursor = next;
+        }
+        if links == 0 || !has_variable {
+            return Ok(None);
+        }
+
+        // 拼接结果以数字开头时（例如`N~th`展开为`0th`）不是合法的标识符，
+        // 这时按照带后缀的字面量输出，可以交给stringify!之类的宏使用
+        let span = first.span();
+        if let Ok(ident) = syn::parse_str::<proc_macro2::Ident>(&pasted) {
+            return Ok(Some((proc_macro2::Ident::new(&ident.to_string(), span).into(), cursor)));
+        }
+        if let Ok(mut literal) = pasted.parse::<proc_macro2::Literal>() {
+            literal.set_span(span);
+            return Ok(Some((literal.into(), cursor)));
+        }
+        Err(syn::Error::new(span, format!("`{}` is not a valid identifier", pasted)))
+    }
+
+    // 把字符串中的`~N`替换为当前的取值，`~N`后面可以再跟一个`~`和后面的内容隔开，例如
+    // `"~N~th"`展开为`"0th"`。字符串中没有需要替换的内容时返回None
+    fn replace_in_str(&self, s: &str, positions: &[Option<usize>]) -> Option<String> {
+        let mut ret = String::new();
+        let mut rest = s;
+        let mut replaced = false;
+        while let Some(pos) = rest.find('~') {
+            let after = &rest[pos + 1..];
+            let name_len = after
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            // `~`后面的名字不是循环变量时（例如`~Name`）原样保留
+            let Some(binding) = self.lookup(&after[..name_len], positions) else {
+                ret.push_str(&rest[..pos + 1]);
+                rest = after;
+                continue;
+            };
+            ret.push_str(&rest[..pos]);
+            ret.push_str(&binding.format_value(false));
+            let after = &after[name_len..];
+            rest = after.strip_prefix('~').unwrap_or(after);
+            replaced = true;
+        }
+        ret.push_str(rest);
+        replaced.then_some(ret)
+    }
+}
+
+// 如果cursor指向`#(...)*`或者带分隔符的`#(...),*`，返回括号内部的位置、分隔符以及`*`之后的位置
+fn repetition_section(cursor: syn::buffer::Cursor) -> Option<(syn::buffer::Cursor, Option<proc_macro2::Punct>, syn::buffer::Cursor)> {
+    let (punct_prefix, cursor_1) = cursor.punct()?;
+    if punct_prefix.as_char() != '#' {
+        return None;
+    }
+    let (section, _, cursor_2) = cursor_1.group(proc_macro2::Delimiter::Parenthesis)?;
+    // 重复的部分后面可以带一个分隔符，例如`#(...),*`，分隔符只出现在两次重复之间
+    let (mut punct_suffix, mut cursor_3) = cursor_2.punct()?;
+    let mut separator = None;
+    if punct_suffix.as_char() != '*' {
+        separator = Some(punct_suffix);
+        (punct_suffix, cursor_3) = cursor_3.punct()?;
+    }
+    if punct_suffix.as_char() != '*' {
+        return None;
+    }
+    Some((section, separator, cursor_3))
+}
+
+// 判断代码中有没有`#(...)*`，嵌套的seq!中的`#(...)*`属于内层，不算
+fn has_section(mut cursor: syn::buffer::Cursor) -> bool {
+    while !cursor.eof() {
+        if repetition_section(cursor).is_some() {
+            return true;
+        }
+        if let Some((.., next)) = nested_seq(cursor) {
+            cursor = next;
+        } else if let Some((inner, _, _, next)) = cursor.any_group() {
+            if has_section(inner) {
+                return true;
+            }
+            cursor = next;
+        } else if let Some((_, next)) = cursor.token_tree() {
+            cursor = next;
+        } else {
+            break;
+        }
+    }
+    false
+}
+
+// 读取嵌套的seq!调用中定义的循环变量，也就是`in`前面的那些标识符
+fn nested_seq_variables(ts: &proc_macro2::TokenStream) -> Vec<proc_macro2::Ident> {
+    // `(I, (T, S)) in ...`这样的模式中的所有标识符都是循环变量
+    fn pattern_idents(ts: proc_macro2::TokenStream, ret: &mut Vec<proc_macro2::Ident>) {
+        for t in ts {
+            match t {
+                proc_macro2::TokenTree::Ident(ident) => ret.push(ident),
+                proc_macro2::TokenTree::Group(g) => pattern_idents(g.stream(), ret),
+                _ => {}
+            }
+        }
+    }
+    let buf = ts.clone().into_iter().collect::<Vec<_>>();
+    let mut ret = Vec::new();
+    for pair in buf.windows(2) {
+        match pair {
+            // 遇到循环体就结束了，循环体里面的`x in iter`和循环变量无关
+            [proc_macro2::TokenTree::Group(g), _] if g.delimiter() == proc_macro2::Delimiter::Brace => break,
+            [proc_macro2::TokenTree::Ident(ident), proc_macro2::TokenTree::Ident(in_token)] if in_token == "in" => {
+                ret.push(ident.clone());
+            }
+            [proc_macro2::TokenTree::Group(g), proc_macro2::TokenTree::Ident(in_token)] if in_token == "in" => {
+                pattern_idents(g.stream(), &mut ret);
+            }
+            _ => {}
+        }
+    }
+    ret
+}
+
+// 如果cursor指向一个`seq!(...)`调用，返回调用的名字、感叹号、括号内部的位置、括号的种类和位置，以及调用之后的位置
+#[allow(clippy::type_complexity)]
+fn nested_seq(cursor: syn::buffer::Cursor) -> Option<(
+    proc_macro2::Ident,
+    proc_macro2::Punct,
+    syn::buffer::Cursor,
+    proc_macro2::Delimiter,
+    proc_macro2::extra::DelimSpan,
+    syn::buffer::Cursor,
+)> {
+    let (name, cursor_1) = cursor.ident()?;
+    let (bang, cursor_2) = cursor_1.punct()?;
+    let (inner, delimiter, delim_span, cursor_3) = cursor_2.any_group()?;
+    if name != "seq" || bang.as_char() != '!' {
+        return None;
+    }
+    Some((name, bang, inner, delimiter, delim_span, cursor_3))
+}
@@ -0,0 +1,43 @@
+// The range can be wrapped in parentheses and followed by the same adapter
+// calls you would write on an iterator, so that register offsets or
+// reverse-ordered items can be generated without doing arithmetic inside the
+// body:
+//
+//     seq!(N in (0..64).step_by(8) { ... })   // 0, 8, 16, ..., 56
+//     seq!(N in (0..8).rev() { ... })         // 7, 6, ..., 0
+//
+// The adapters are applied in the order they are written, exactly like on an
+// iterator. A shorter `step` form is also accepted:
+//
+//     seq!(N in 0..64 step 8 { ... })
+
+use seq::seq;
+
+seq!(N in (0..64).step_by(8) {
+    const OFFSETS: [usize; 8] = [#(N,)*];
+});
+
+seq!(N in (0..4).rev() {
+    const REVERSED: [usize; 4] = [#(N,)*];
+});
+
+seq!(N in (0..=9).step_by(3).rev() {
+    const STEPPED_THEN_REVERSED: [usize; 4] = [#(N,)*];
+});
+
+seq!(N in (0..9).rev().step_by(3) {
+    const REVERSED_THEN_STEPPED: [usize; 3] = [#(N,)*];
+});
+
+seq!(N in 0..16 step 4 {
+    struct Register~N;
+});
+
+fn main() {
+    assert_eq!(OFFSETS, [0, 8, 16, 24, 32, 40, 48, 56]);
+    assert_eq!(REVERSED, [3, 2, 1, 0]);
+    assert_eq!(STEPPED_THEN_REVERSED, [9, 6, 3, 0]);
+    assert_eq!(REVERSED_THEN_STEPPED, [8, 5, 2]);
+
+    let _ = (Register0, Register4, Register8, Register12);
+}
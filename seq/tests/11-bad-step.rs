@@ -0,0 +1,15 @@
+// A step of zero would never make progress, and only the `step_by`, `rev`,
+// `enumerate` and `zip` adapters are understood. Both mistakes are reported at
+// the offending token.
+
+use seq::seq;
+
+seq!(N in (0..8).step_by(0) {
+    fn f~N() {}
+});
+
+seq!(N in (0..8).skip(1) {
+    fn g~N() {}
+});
+
+fn main() {}
@@ -0,0 +1,69 @@
+// Range bounds are not limited to plain decimal literals. They may be
+// negative, and they may be simple arithmetic on integer literals, which is
+// evaluated when the macro expands:
+//
+//     seq!(N in -4..4 { ... })
+//     seq!(N in 0..1 << 4 { ... })
+//     seq!(N in (2 * 3)..=(16 - 1) { ... })
+//
+// Bounds may be written in hexadecimal, octal or binary. The values are still
+// produced in decimal, so `f~N` with `0x0..0x10` produces f0 through f15.
+// Writing `keep_radix` after the range produces them in the same base as the
+// bounds, zero-padded to the same number of digits. Pasted identifiers get
+// the digits without the prefix, so `Reg~N` with `0x00..0x10 keep_radix`
+// produces Reg00 through Reg0f.
+
+use seq::seq;
+
+seq!(N in -4..4 {
+    const SIGNED: [i32; 8] = [#(N,)*];
+});
+
+seq!(N in 0..1 << 4 {
+    const SHIFTED: [usize; 16] = [#(N,)*];
+});
+
+seq!(N in (2 * 3)..=(16 - 1) % 10 + 4 {
+    const ARITHMETIC: [usize; 4] = [#(N,)*];
+});
+
+seq!(N in 10 - 2 * 3..(1 + 1) * 3 {
+    const PRECEDENCE: [usize; 2] = [#(N,)*];
+});
+
+seq!(N in 0x0..0x10 {
+    #(
+        fn f~N() -> usize {
+            N
+        }
+    )*
+});
+
+seq!(N in 0x00..0x10 keep_radix {
+    #[derive(Debug)]
+    enum Reg {
+        #(
+            Reg~N = N,
+        )*
+    }
+});
+
+seq!(N in -0x2..0x2 {
+    const SIGNED_HEX: [i32; 4] = [#(N,)*];
+});
+
+seq!(N in 0b00..=0b11 keep_radix {
+    const BITS: [&str; 4] = [#(stringify!(N),)*];
+});
+
+fn main() {
+    assert_eq!(SIGNED, [-4, -3, -2, -1, 0, 1, 2, 3]);
+    assert_eq!(SHIFTED.len(), 16);
+    assert_eq!(ARITHMETIC, [6, 7, 8, 9]);
+    assert_eq!(PRECEDENCE, [4, 5]);
+    assert_eq!(f15(), 15);
+    assert_eq!(Reg::Reg0a as u8, 10);
+    assert_eq!(format!("{:?}", Reg::Reg0f), "Reg0f");
+    assert_eq!(SIGNED_HEX, [-2, -1, 0, 1]);
+    assert_eq!(BITS, ["0b00", "0b01", "0b10", "0b11"]);
+}
@@ -0,0 +1,14 @@
+// Bounds that cannot be evaluated, such as an overflowing shift or a division
+// by zero, are reported at the operator.
+
+use seq::seq;
+
+seq!(N in 0..1 << 70 {
+    fn f~N() {}
+});
+
+seq!(N in 0..4 / 0 {
+    fn g~N() {}
+});
+
+fn main() {}
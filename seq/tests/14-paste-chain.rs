@@ -0,0 +1,32 @@
+// Pasting is not limited to a single `prefix~N`. Any chain of identifiers
+// joined by `~` without whitespace is concatenated into one identifier, and
+// every link equal to the loop variable is replaced by the current value:
+//
+//     f~N~_suffix    =>  f0_suffix, f1_suffix, ...
+//     get~N~_or~N    =>  get0_or0, get1_or1, ...
+//
+// A chain that starts with the variable, such as `N~th`, does not form a valid
+// identifier. It is emitted as an integer literal with a suffix (`0th`) which
+// other macros like stringify! can consume.
+
+use seq::seq;
+
+seq!(N in 0..3 {
+    #(
+        fn f~N~_suffix() -> usize {
+            N
+        }
+
+        fn get~N~_or~N() -> usize {
+            N * 2
+        }
+    )*
+
+    const ORDINALS: [&str; 3] = [#(stringify!(N~th),)*];
+});
+
+fn main() {
+    assert_eq!(f0_suffix() + f1_suffix() + f2_suffix(), 3);
+    assert_eq!(get2_or2(), 4);
+    assert_eq!(ORDINALS, ["0th", "1th", "2th"]);
+}
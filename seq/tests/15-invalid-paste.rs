@@ -0,0 +1,11 @@
+// A pasted chain that produces neither an identifier nor a literal, for
+// example because the value is negative, is reported at the start of the
+// chain instead of panicking inside the macro.
+
+use seq::seq;
+
+seq!(N in -1..1 {
+    fn f~N() {}
+});
+
+fn main() {}
@@ -0,0 +1,41 @@
+// A bare `N` expands to an unsuffixed integer literal, whose type is left to
+// inference. Writing `N as u8` instead expands to the suffixed literal `0u8`,
+// so method calls such as `(N as u32).pow(2)` and `let x = N as i64;` have a
+// concrete type.
+//
+// Inside string literals, `~N` is replaced by the current value. This makes
+// it possible to generate names in attributes and doc comments. A trailing
+// `~` separates the value from the text that follows, as in "~N~th".
+
+use seq::seq;
+
+seq!(N in 0..4 {
+    #(
+        #[doc = "Returns the square of ~N."]
+        #[allow(dead_code)]
+        fn square~N() -> u32 {
+            (N as u32).pow(2)
+        }
+    )*
+
+    const NAMES: [&str; 4] = [#("field_~N",)*];
+    const ORDINALS: [&str; 4] = [#("~N~th",)*];
+    const UNTOUCHED: &str = "~Name and N stay as they are";
+});
+
+seq!(N in 0x0..0x3 {
+    const BYTES: [u8; 3] = [#(N as u8,)*];
+});
+
+fn main() {
+    seq!(N in 3..4 {
+        let y = N as i64;
+    });
+    assert_eq!(y.pow(2), 9);
+
+    assert_eq!(square3(), 9);
+    assert_eq!(NAMES, ["field_0", "field_1", "field_2", "field_3"]);
+    assert_eq!(ORDINALS, ["0th", "1th", "2th", "3th"]);
+    assert_eq!(UNTOUCHED, "~Name and N stay as they are");
+    assert_eq!(BYTES, [0, 1, 2]);
+}
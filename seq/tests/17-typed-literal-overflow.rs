@@ -0,0 +1,10 @@
+// A value that does not fit in the requested type is reported at the
+// variable.
+
+use seq::seq;
+
+seq!(N in 250..=256 {
+    const BYTES: [u8; 7] = [#(N as u8,)*];
+});
+
+fn main() {}
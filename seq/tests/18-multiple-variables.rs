@@ -0,0 +1,48 @@
+// More than one loop variable can be declared, separated by commas. The body
+// is expanded once for every combination of values, with the first variable
+// in the outer loop, which makes it easy to generate 2-D tables of impls:
+//
+//     seq!(N in 0..4, M in 0..4 {
+//         #(
+//             impl Convert<B~M> for B~N { ... }
+//         )*
+//     });
+//
+// Every variable can use its own range syntax.
+
+use seq::seq;
+
+pub trait Convert<T> {
+    fn convert(&self) -> (usize, usize);
+}
+
+seq!(N in 0..4 {
+    #(
+        pub struct B~N;
+    )*
+});
+
+seq!(N in 0..4, M in (0..4).rev() {
+    #(
+        impl Convert<B~M> for B~N {
+            fn convert(&self) -> (usize, usize) {
+                (N, M)
+            }
+        }
+    )*
+
+    const PAIRS: [(usize, usize); 16] = [#((N, M),)*];
+});
+
+seq!(ROW in 0..2, COL in 0..3 step 2 {
+    const CELL_~ROW~_~COL: &str = "row ~ROW, col ~COL";
+});
+
+fn main() {
+    assert_eq!(Convert::<B3>::convert(&B1), (1, 3));
+    assert_eq!(PAIRS[0], (0, 3));
+    assert_eq!(PAIRS[1], (0, 2));
+    assert_eq!(PAIRS[4], (1, 3));
+    assert_eq!(PAIRS[15], (3, 0));
+    assert_eq!(CELL_1_2, "row 1, col 2");
+}
@@ -0,0 +1,41 @@
+// A seq! invocation inside the body of another one is left for the inner
+// macro to expand. Repeat sections `#(...)*` inside the inner invocation
+// belong to the inner macro, and a variable that the inner invocation
+// declares again shadows the outer one. Outer variables that are not shadowed
+// are substituted as usual, including in the range of the inner invocation.
+
+use seq::seq;
+
+seq!(N in 1..4 {
+    #(
+        mod triangle~N {
+            use seq::seq;
+
+            seq!(M in 0..N {
+                pub const ROW: [usize; N] = [#(M * N,)*];
+            });
+        }
+    )*
+});
+
+seq!(N in 0..2 {
+    #(
+        mod shadow~N {
+            use seq::seq;
+
+            seq!(N in 5..7 {
+                #(
+                    pub fn f~N() -> usize {
+                        N
+                    }
+                )*
+            });
+        }
+    )*
+});
+
+fn main() {
+    assert_eq!(triangle1::ROW, [0]);
+    assert_eq!(triangle3::ROW, [0, 3, 6]);
+    assert_eq!(shadow0::f5() + shadow1::f6(), 11);
+}
@@ -0,0 +1,59 @@
+// A repetition section may be followed by a separator, which is emitted
+// between the iterations but not after the last one:
+//
+//     #(N),*     =>  0, 1, 2
+//     #(N)+*     =>  0 + 1 + 2
+//
+// Each section only iterates over the loop variables it mentions, so with
+// several variables different sections can repeat over different ranges. A
+// section that mentions no variable repeats once for every combination.
+//
+// The position of the iteration is available separately from the value by
+// binding a pair and ending the range with `.enumerate()`:
+//
+//     seq!((I, N) in (0..64).step_by(16).enumerate() { ... })
+//
+// A section found in an earlier group must still be recognised when a later
+// group in the body has none; otherwise the whole body would be repeated.
+
+use seq::seq;
+
+seq!(N in 0..4 {
+    const LIST: [usize; 4] = [#(N),*];
+    const SUM: usize = #(N)+*;
+    fn unit() {}
+});
+
+seq!(N in 0..3, M in 0..2 {
+    pub struct Wide {
+        #(pub a~N: u8,)*
+    }
+
+    pub struct Narrow {
+        #(pub b~M: u8,)*
+    }
+
+    const GRID: [(usize, usize); 6] = [#((N, M)),*];
+});
+
+seq!((I, N) in (0..64).step_by(16).enumerate() {
+    const OFFSETS: [(usize, usize); 4] = [#((I, N)),*];
+    #(
+        const REG~I: usize = N;
+    )*
+});
+
+fn main() {
+    unit();
+    assert_eq!(LIST, [0, 1, 2, 3]);
+    assert_eq!(SUM, 6);
+
+    let wide = Wide { a0: 0, a1: 1, a2: 2 };
+    let narrow = Narrow { b0: 0, b1: 1 };
+    assert_eq!(wide.a2 + narrow.b1, 3);
+    assert_eq!(GRID[1], (0, 1));
+    assert_eq!(GRID[5], (2, 1));
+
+    assert_eq!(OFFSETS, [(0, 0), (1, 16), (2, 32), (3, 48)]);
+    assert_eq!(REG3, 48);
+}
@@ -0,0 +1,20 @@
+// The binding pattern has to match the shape of the items produced by the
+// sequence: `.enumerate()` and `.zip(...)` turn every item into a pair, so
+// they need an `(a, b)` pattern, and a plain range or list needs a single
+// identifier.
+
+use seq::seq;
+
+seq!((I, N) in (0..4).rev() {
+    fn f~N() {}
+});
+
+seq!(N in (0..4).enumerate() {
+    fn g~N() {}
+});
+
+seq!((I, N) in [u8, u16].zip([i8, i16]).enumerate() {
+    fn h~I() {}
+});
+
+fn main() {}
@@ -0,0 +1,43 @@
+// Arithmetic on the loop variable is often needed as a single literal, for
+// example in array lengths or match patterns where runtime arithmetic is not
+// allowed. `~{expr}` is evaluated when the macro expands and replaced by an
+// integer literal:
+//
+//     [u8; ~{N * 2}]      =>  [u8; 6]      when N is 3
+//     ~{1 << N} => ...    =>  8 => ...
+//
+// The same braces can be used as a link of a pasted identifier:
+//
+//     REG~{N * 4}         =>  REG12
+//
+// The expression supports the same operators as range bounds and may refer
+// to every loop variable, including an enumerate index.
+
+use seq::seq;
+
+seq!(N in 0..4 {
+    #(
+        pub struct Buf~N(pub [u8; ~{N * 2}]);
+
+        pub const REG~{N * 4}: usize = ~{N + 1};
+    )*
+
+    pub fn mask_name(mask: u32) -> usize {
+        match mask {
+            #(~{1 << N} => N,)*
+            _ => usize::MAX,
+        }
+    }
+});
+
+seq!((I, N) in (0x10..0x40).step_by(0x10).enumerate() {
+    const SHIFTED: [usize; 3] = [#(~{N + I}),*];
+});
+
+fn main() {
+    assert_eq!(Buf3([0; 6]).0.len(), 6);
+    assert_eq!(REG0 + REG12, 5);
+    assert_eq!(mask_name(8), 3);
+    assert_eq!(mask_name(3), usize::MAX);
+    assert_eq!(SHIFTED, [0x10, 0x21, 0x32]);
+}
@@ -0,0 +1,10 @@
+// Expressions in `~{...}` that cannot be evaluated are reported at the
+// operator, just like range bounds.
+
+use seq::seq;
+
+seq!(N in 0..4 {
+    const X: [usize; 4] = [#(~{8 / N}),*];
+});
+
+fn main() {}
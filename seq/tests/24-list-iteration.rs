@@ -0,0 +1,80 @@
+// Besides integer ranges, seq! can iterate over an explicit list of items
+// written in square brackets. Each item may be an identifier, a type or an
+// expression, and is substituted wherever the loop variable appears, so the
+// same body can be stamped out once per type:
+//
+//     seq!(T in [u8, u16, u32, u64] {
+//         impl Foo for T {}
+//     });
+//
+// Lists support the same adapters as parenthesized ranges, plus `zip(...)`
+// which pairs them with another list or range. A nested pattern such as
+// `(I, (T, S))` destructures `.zip(...).enumerate()`.
+//
+// Items that are plain unsuffixed integers stay integers, so they can still
+// take part in `~{...}` arithmetic. Every other item, including suffixed
+// literals like `1u8` and expressions like `1 + 1`, is substituted unchanged.
+
+use seq::seq;
+
+trait Width {
+    const BITS: u32;
+}
+
+seq!(T in [u8, u16, u32, u64] {
+    impl Width for T {
+        const BITS: u32 = T::MAX.count_ones();
+    }
+});
+
+seq!(T in [u8, i32, bool] {
+    fn parse_~T(v: T) -> T {
+        v
+    }
+});
+
+seq!((I, (U, S)) in [u8, u16, u32].zip([i8, i16, i32]).enumerate() {
+    const SIGNED_~I: [&str; 2] = [stringify!(U), stringify!(S)];
+});
+
+seq!((T, N) in [u8, u16].zip((10..20).step_by(5)) {
+    fn scaled_~T(v: T) -> T {
+        v * N
+    }
+});
+
+seq!(N in [1, 3, 0x10] {
+    const SQUARE_~N: u32 = ~{N * N};
+});
+
+seq!(X in [1u8, 300u16, 2i64] {
+    fn suffixed() -> [&'static str; 3] {
+        [#(std::any::type_name_of_val(&X),)*]
+    }
+});
+
+seq!(X in [1 + 1, 3] {
+    const EXPRESSIONS: [&str; 2] = [#(stringify!(X),)*];
+});
+
+fn main() {
+    assert_eq!(<u8 as Width>::BITS, 8);
+    assert_eq!(<u64 as Width>::BITS, 64);
+
+    assert_eq!(parse_u8(1), 1);
+    assert_eq!(parse_i32(-1), -1);
+    assert!(parse_bool(true));
+
+    assert_eq!(SIGNED_0, ["u8", "i8"]);
+    assert_eq!(SIGNED_2, ["u32", "i32"]);
+
+    assert_eq!(scaled_u8(2), 20);
+    assert_eq!(scaled_u16(2), 30);
+
+    assert_eq!(SQUARE_1, 1);
+    assert_eq!(SQUARE_3, 9);
+    assert_eq!(SQUARE_16, 256);
+
+    assert_eq!(suffixed(), ["u8", "u16", "i64"]);
+    assert_eq!(EXPRESSIONS, ["1 + 1", "3"]);
+}
@@ -0,0 +1,75 @@
+// seq! refuses to expand its body more than 65536 times by default, and
+// warns when the body is expanded more than 16384 times. Both numbers can be
+// changed with `limit = ...` and `warn = ...` options, written before the
+// loop variables:
+//
+//     seq!(limit = 100000, N in 0..100000 { ... })
+//
+// The count is the product over all variables, since multiple variables
+// expand as a cartesian product.
+//
+// The limit applies to the number of values after `step_by` and `step`, so
+// a wide range with a large step is fine.
+//
+// The warning can only be attached where an item is allowed. An expansion in
+// expression position, or inside an impl or trait block, above the threshold
+// still compiles, just without the warning.
+
+use seq::seq;
+
+seq!(limit = 4, N in 0..4 {
+    const A~N: usize = N;
+});
+
+seq!(limit = 6, warn = 6, N in 0..2, M in 0..3 {
+    const B~N~M: usize = N * M;
+});
+
+seq!(N in (0..100000).step_by(1000).rev() {
+    const WIDE: [usize; 100] = [#(N,)*];
+});
+
+seq!(N in 0..1000000 step 100000 {
+    const WIDER: [usize; 10] = [#(N,)*];
+});
+
+pub struct S;
+
+impl S {
+    seq!(warn = 2, N in 0..4 {
+        pub fn f~N() -> usize {
+            N
+        }
+    });
+}
+
+pub trait T {
+    seq!(warn = 2, N in 0..4 {
+        fn g~N(&self) -> usize {
+            N
+        }
+    });
+}
+
+impl T for S {}
+
+// A variable called `limit` still works.
+seq!(limit in 0..2 {
+    const C~limit: usize = limit;
+});
+
+fn main() {
+    let v: [u32; 20000] = seq!(N in 0..20000 { [#(N,)*] });
+    assert_eq!(v[19999], 19999);
+
+    let w: [u32; 3] = seq!(warn = 2, N in 0..3 { [#(N,)*] });
+    assert_eq!(w, [0, 1, 2]);
+
+    assert_eq!(A3, 3);
+    assert_eq!(B12, 2);
+    assert_eq!(C1, 1);
+    assert_eq!(S::f3(), 3);
+    assert_eq!(S.g2(), 2);
+    assert_eq!((WIDE[0], WIDE[99]), (99000, 0));
+    assert_eq!(WIDER[9], 900000);
+}
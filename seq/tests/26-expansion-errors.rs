@@ -0,0 +1,32 @@
+// Huge ranges are rejected with an error pointing at the range instead of
+// making rustc hang, and so are ranges whose start is past their end and
+// sequences that produce no values at all, which used to silently expand to
+// nothing.
+
+use seq::seq;
+
+seq!(N in 0..10000000 {
+    fn a~N() {}
+});
+
+seq!(N in 0..300, M in 0..300 {
+    fn b~N~M() {}
+});
+
+seq!(limit = 4, N in 0..=4 {
+    fn c~N() {}
+});
+
+seq!(N in 5..0 {
+    fn d~N() {}
+});
+
+seq!(N in 0..0 {
+    fn e~N() {}
+});
+
+seq!(T in [] {
+    fn f~T() {}
+});
+
+fn main() {}
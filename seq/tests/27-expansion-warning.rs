@@ -0,0 +1,12 @@
+// Expanding the body more often than the warning threshold compiles, but
+// produces a warning at the range.
+
+use seq::seq;
+
+seq!(warn = 2, N in 0..3 {
+    struct W~N;
+});
+
+fn main() {
+    let _: () = W0;
+}
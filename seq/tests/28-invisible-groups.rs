@@ -0,0 +1,47 @@
+// When a macro_rules macro passes an `$e:expr`, `$n:literal` or `$t:ty`
+// fragment into seq!, the fragment arrives wrapped in an invisible group (a
+// group with `Delimiter::None`). seq! passes these groups through intact,
+// both inside and outside of `#(...)*` sections, and treats them like
+// parentheses when evaluating range bounds and `~{...}`, so `$e * 2` with
+// `$e` being `1 + 1` is 4 rather than 3.
+//
+// Type fragments are looked through when producing `N as $t` literals.
+
+use seq::seq;
+
+macro_rules! scaled {
+    ($name:ident, $e:expr) => {
+        seq!(N in 0..$e * 2 {
+            const $name: [usize; ~{$e * 2}] = [#(~{$e * N},)*];
+        });
+    };
+}
+
+scaled!(SCALED, 1 + 1);
+
+macro_rules! whole_body {
+    ($e:expr) => {
+        seq!(N in 1..3 {
+            const WHOLE~N: i32 = ~{$e * N};
+        });
+    };
+}
+
+whole_body!(2 + 3);
+
+macro_rules! typed {
+    ($t:ty) => {
+        seq!(N in 0..2 {
+            const TYPED~N: $t = N as $t;
+        });
+    };
+}
+
+typed!(u8);
+
+fn main() {
+    assert_eq!(SCALED, [0, 2, 4, 6]);
+    assert_eq!(WHOLE1, 5);
+    assert_eq!(WHOLE2, 10);
+    assert_eq!(TYPED1, 1u8);
+}
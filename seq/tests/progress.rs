@@ -10,4 +10,23 @@ fn tests() {
     t.pass("tests/07-inclusive-range.rs");
     t.compile_fail("tests/08-ident-span.rs");
     t.pass("tests/09-interaction-with-macrorules.rs");
+    t.pass("tests/10-step-and-rev.rs");
+    t.compile_fail("tests/11-bad-step.rs");
+    t.pass("tests/12-expression-bounds.rs");
+    t.compile_fail("tests/13-bound-overflow.rs");
+    t.pass("tests/14-paste-chain.rs");
+    t.compile_fail("tests/15-invalid-paste.rs");
+    t.pass("tests/16-typed-and-string-literals.rs");
+    t.compile_fail("tests/17-typed-literal-overflow.rs");
+    t.pass("tests/18-multiple-variables.rs");
+    t.pass("tests/19-nested-seq.rs");
+    t.pass("tests/20-repetition-sections.rs");
+    t.compile_fail("tests/21-enumerate-errors.rs");
+    t.pass("tests/22-inline-arithmetic.rs");
+    t.compile_fail("tests/23-inline-arithmetic-errors.rs");
+    t.pass("tests/24-list-iteration.rs");
+    t.pass("tests/25-expansion-limits.rs");
+    t.compile_fail("tests/26-expansion-errors.rs");
+    t.compile_fail("tests/27-expansion-warning.rs");
+    t.pass("tests/28-invisible-groups.rs");
 }